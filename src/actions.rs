@@ -0,0 +1,374 @@
+// What can be done with results that doesn't depend on the platform: copying their
+// paths, moving them, and checking paths before they are handed to another program or
+// the Recycle Bin. Everything here works on plain paths, so it can be tested anywhere.
+//
+// Every name on disk is indexed, however odd, so paths are checked here rather than
+// names at scan time. Nothing is run through a shell: a path goes to the program as a
+// file or working directory, or as the one quoted argument below, and only once it has
+// passed checked_path.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[cfg(windows)]
+pub const LINE_ENDING: &str = "\r\n";
+#[cfg(not(windows))]
+pub const LINE_ENDING: &str = "\n";
+
+// One per line, for the clipboard
+pub fn lines<S: AsRef<str>>(items: &[S]) -> String {
+    items.iter().map(AsRef::as_ref).collect::<Vec<_>>().join(LINE_ENDING)
+}
+
+// The folder a path is in, by its last separator. Paths from file lists and catalogs
+// are Windows paths whatever the platform, so this doesn't go through std::path.
+pub fn parent_of(path: &str) -> &str {
+    let trimmed = path.trim_end_matches(['\\', '/']);
+    match trimmed.rfind(['\\', '/']) {
+        // A root keeps its separator, as in C:\ or /
+        Some(i) if i == 0 || trimmed[..i].ends_with(':') => &trimmed[..=i],
+        Some(i) => &trimmed[..i],
+        None => "",
+    }
+}
+
+// A path fit to hand to another program: absolute (so it can't pass for an option or
+// be looked up on PATH), without control characters (a NUL would cut it short) and
+// without device or stream syntax that would point it somewhere else.
+pub fn check_path(path: &str) -> Result<(), String> {
+    if path.is_empty() {
+        return Err("No path".to_string());
+    }
+    if path.chars().any(char::is_control) {
+        return Err(format!("{:?} contains control characters", path));
+    }
+    // Either slash works in these prefixes
+    let prefix: String = path.chars().take(4).map(|c| if c == '/' { '\\' } else { c }).collect();
+    if prefix == "\\\\?\\" || prefix == "\\\\.\\" {
+        return Err(format!("{} is a device path", path));
+    }
+
+    if let Some(rest) = windows_path_rest(path) {
+        // Windows names can't have a colon; past the drive one means a file:stream
+        if rest.contains(':') {
+            return Err(format!("{} names an alternate data stream", path));
+        }
+        Ok(())
+    } else if cfg!(not(windows)) && path.starts_with('/') {
+        // On Windows this would be relative to the current drive, and look like a switch
+        Ok(())
+    } else {
+        Err(format!("{} is not an absolute path", path))
+    }
+}
+
+// For a drive (C:\...) or network (\\server\...) path, what follows the drive letter
+fn windows_path_rest(path: &str) -> Option<&str> {
+    let bytes = path.as_bytes();
+    if bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && matches!(bytes[2], b'\\' | b'/') {
+        Some(&path[2..])
+    } else if path.starts_with("\\\\") {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+// `\\?\C:\x` as `C:\x` and `\\?\UNC\server\share` as `\\server\share`, which is how
+// canonicalize() answers on Windows and what Explorer doesn't always understand
+pub fn strip_verbatim(path: &str) -> String {
+    if let Some(rest) = path.strip_prefix("\\\\?\\UNC\\") {
+        format!("\\\\{}", rest)
+    } else if let Some(rest) = path.strip_prefix("\\\\?\\") {
+        rest.to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+// The path as it is on disk now, links resolved, after checking both what was asked
+// for and what it resolved to
+pub fn checked_path(path: &str) -> Result<String, String> {
+    check_path(path)?;
+    let resolved = std::fs::canonicalize(path).map_err(|e| format!("{}: {}", path, e))?;
+    let resolved = strip_verbatim(&resolved.to_string_lossy());
+    check_path(&resolved)?;
+    Ok(resolved)
+}
+
+// Explorer's own argument syntax for opening a folder with one entry selected. Windows
+// names can't contain a quote, so one in the path is an attempt to end the quoting.
+pub fn explorer_select_argument(path: &str) -> Result<String, String> {
+    check_path(path)?;
+    if windows_path_rest(path).is_none() {
+        return Err(format!("{} is not a Windows path", path));
+    }
+    if path.contains('"') {
+        return Err(format!("{} contains a quote", path));
+    }
+    Ok(format!("/select,\"{}\"", path))
+}
+
+#[derive(Debug, Default)]
+pub struct MoveReport {
+    pub moved: Vec<String>, // Source paths that are now in the folder
+    pub failed: Vec<String>, // Why the others aren't
+}
+
+// Move each path into `folder` under its own name. Nothing there is overwritten, and a
+// path that can't be moved doesn't stop the rest.
+pub fn move_into(paths: &[String], folder: &Path) -> MoveReport {
+    let mut report = MoveReport::default();
+    if !folder.is_dir() {
+        report.failed.push(format!("{} is not a folder", folder.display()));
+        return report;
+    }
+    for path in paths {
+        match move_one(Path::new(path), folder) {
+            Ok(()) => report.moved.push(path.clone()),
+            Err(e) => report.failed.push(format!("{}: {}", path, e)),
+        }
+    }
+    report
+}
+
+fn move_one(source: &Path, folder: &Path) -> Result<(), String> {
+    let name = source.file_name().ok_or("Not a file or folder")?;
+    let metadata = std::fs::symlink_metadata(source).map_err(|e| e.to_string())?;
+    let target = folder.join(name);
+    if target.exists() || std::fs::symlink_metadata(&target).is_ok() {
+        return Err(format!("{} already exists", target.display()));
+    }
+    if metadata.is_dir() && absolute(folder).starts_with(absolute(source)) {
+        return Err("A folder can't be moved into itself".to_string());
+    }
+
+    match std::fs::rename(source, &target) {
+        Ok(()) => Ok(()),
+        // Another drive: files are copied over, folders would need a whole tree copied
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices && metadata.is_file() => {
+            std::fs::copy(source, &target)
+                .and_then(|_| std::fs::remove_file(source))
+                .map_err(|e| e.to_string())
+        }
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            Err("Folders can't be moved to another drive".to_string())
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+// Everything to be sent to the Recycle Bin must still be there, so a stale result
+// doesn't fail halfway through
+pub fn check_trash(paths: &[String]) -> Result<(), String> {
+    match paths.iter().find(|p| std::fs::symlink_metadata(p).is_err()) {
+        Some(missing) => Err(format!("{} no longer exists", missing)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // A fresh folder under the system temp folder, removed again when dropped
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Scratch {
+            let dir = std::env::temp_dir().join(format!("deep_search_{}_{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Scratch(dir)
+        }
+
+        fn file(&self, name: &str, contents: &str) -> String {
+            let path = self.0.join(name);
+            fs::write(&path, contents).unwrap();
+            path.display().to_string()
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn lines_puts_one_item_per_line() {
+        assert_eq!(lines(&["C:\\a.txt", "C:\\b c.txt"]), format!("C:\\a.txt{}C:\\b c.txt", LINE_ENDING));
+        assert_eq!(lines(&["only"]), "only");
+        assert_eq!(lines::<&str>(&[]), "");
+    }
+
+    #[test]
+    fn parent_of_keeps_roots() {
+        assert_eq!(parent_of("C:\\a\\report.pdf"), "C:\\a");
+        assert_eq!(parent_of("C:\\a\\folder\\"), "C:\\a");
+        assert_eq!(parent_of("C:\\report.pdf"), "C:\\");
+        assert_eq!(parent_of("[box]\\C:\\a\\notes.txt"), "[box]\\C:\\a");
+        assert_eq!(parent_of("/home/me/notes.txt"), "/home/me");
+        assert_eq!(parent_of("/notes.txt"), "/");
+        assert_eq!(parent_of("notes.txt"), "");
+    }
+
+    #[test]
+    fn check_path_takes_ordinary_and_odd_names() {
+        // All legitimate names, which the scan used to leave out
+        for path in [
+            "C:\\Users\\me\\Desktop\\Shortcut.lnk",
+            "C:\\ProgramData\\{6D809377-6AF0-444B-8957-A3773F02200E}",
+            "C:\\a\\Control Panel.{21EC2020-3AEA-1069-A2DD-08002B30309D}",
+            "C:\\a\\name with spaces, commas; & carets^ and %PATH%.txt",
+            "D:/mixed/slashes.txt",
+            "\\\\server\\share\\file.txt",
+        ] {
+            assert_eq!(check_path(path), Ok(()), "{}", path);
+        }
+        #[cfg(not(windows))]
+        assert_eq!(check_path("/home/me/10:30 notes.txt"), Ok(()));
+    }
+
+    #[test]
+    fn check_path_refuses_what_could_be_read_as_something_else() {
+        for path in [
+            "",
+            "-n",
+            "report.pdf",
+            "..\\..\\Windows\\System32\\cmd.exe",
+            "C:\\a\\report.pdf\0C:\\Windows\\System32\\calc.exe",
+            "C:\\a\\line\nbreak.txt",
+            "C:\\a\\notes.txt:hidden.exe",
+            "C:\\a\\::{20D04FE0-3AEA-1069-A2D8-08002B30309D}",
+            "\\\\?\\C:\\a\\notes.txt",
+            "\\\\.\\PhysicalDrive0",
+            "//./PhysicalDrive0",
+        ] {
+            assert!(check_path(path).is_err(), "{:?}", path);
+        }
+        #[cfg(windows)]
+        assert!(check_path("/select,C:\\Windows").is_err());
+    }
+
+    #[test]
+    fn explorer_argument_quotes_the_whole_path() {
+        assert_eq!(explorer_select_argument("C:\\a\\report.pdf").unwrap(), "/select,\"C:\\a\\report.pdf\"");
+        assert_eq!(
+            explorer_select_argument("C:\\a\\x, \\root,Windows & more.txt").unwrap(),
+            "/select,\"C:\\a\\x, \\root,Windows & more.txt\""
+        );
+    }
+
+    #[test]
+    fn explorer_argument_cant_be_broken_out_of() {
+        // A quote would end the path and let the rest through as more switches
+        assert!(explorer_select_argument("C:\\a\\x\",/root,\"C:\\Windows").is_err());
+        assert!(explorer_select_argument("C:\\a\\x\" /e,\"C:\\Windows").is_err());
+        assert!(explorer_select_argument("/root,C:\\Windows").is_err());
+    }
+
+    #[test]
+    fn strip_verbatim_gives_plain_paths() {
+        assert_eq!(strip_verbatim("\\\\?\\C:\\a\\notes.txt"), "C:\\a\\notes.txt");
+        assert_eq!(strip_verbatim("\\\\?\\UNC\\server\\share\\x"), "\\\\server\\share\\x");
+        assert_eq!(strip_verbatim("C:\\a"), "C:\\a");
+    }
+
+    #[test]
+    fn checked_path_resolves_existing_paths_only() {
+        let scratch = Scratch::new("checked");
+        // Names that look like options or shell syntax are still just names
+        let odd = scratch.file("-rf {x} ; $(echo) & notes.txt", "odd");
+        assert_eq!(checked_path(&odd).unwrap(), strip_verbatim(&absolute(Path::new(&odd)).to_string_lossy()));
+
+        let gone = scratch.0.join("gone.txt").display().to_string();
+        assert!(checked_path(&gone).is_err());
+        assert!(checked_path("notes.txt").is_err());
+    }
+
+    #[test]
+    fn move_into_moves_files_and_folders() {
+        let scratch = Scratch::new("move");
+        let file = scratch.file("report.pdf", "pdf");
+        let folder = scratch.0.join("old");
+        fs::create_dir(&folder).unwrap();
+        fs::write(folder.join("inner.txt"), "inner").unwrap();
+        let target = scratch.0.join("target");
+        fs::create_dir(&target).unwrap();
+
+        let paths = vec![file.clone(), folder.display().to_string()];
+        let report = move_into(&paths, &target);
+        assert_eq!(report.moved, paths);
+        assert!(report.failed.is_empty());
+        assert_eq!(fs::read_to_string(target.join("report.pdf")).unwrap(), "pdf");
+        assert_eq!(fs::read_to_string(target.join("old").join("inner.txt")).unwrap(), "inner");
+        assert!(!Path::new(&file).exists());
+    }
+
+    #[test]
+    fn move_into_never_overwrites() {
+        let scratch = Scratch::new("overwrite");
+        let file = scratch.file("notes.txt", "new");
+        let target = scratch.0.join("target");
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join("notes.txt"), "old").unwrap();
+        let other = scratch.file("other.txt", "other");
+
+        let report = move_into(&[file.clone(), other.clone()], &target);
+        assert_eq!(report.moved, vec![other]);
+        assert_eq!(report.failed.len(), 1);
+        assert!(report.failed[0].contains("already exists"));
+        assert_eq!(fs::read_to_string(target.join("notes.txt")).unwrap(), "old");
+        assert_eq!(fs::read_to_string(&file).unwrap(), "new");
+    }
+
+    #[test]
+    fn move_into_refuses_a_folder_into_itself() {
+        let scratch = Scratch::new("itself");
+        let folder = scratch.0.join("folder");
+        let inside = folder.join("inside");
+        fs::create_dir_all(&inside).unwrap();
+
+        let report = move_into(&[folder.display().to_string()], &inside);
+        assert!(report.moved.is_empty());
+        assert!(report.failed[0].contains("into itself"));
+        assert!(inside.is_dir());
+    }
+
+    #[test]
+    fn move_into_reports_missing_sources_and_targets() {
+        let scratch = Scratch::new("missing");
+        let file = scratch.file("a.txt", "a");
+        let gone = scratch.0.join("gone.txt").display().to_string();
+
+        let report = move_into(std::slice::from_ref(&file), &scratch.0.join("nowhere"));
+        assert!(report.moved.is_empty());
+        assert!(report.failed[0].contains("is not a folder"));
+        assert!(Path::new(&file).exists());
+
+        let target = scratch.0.join("target");
+        fs::create_dir(&target).unwrap();
+        let report = move_into(&[gone, file], &target);
+        assert_eq!(report.moved.len(), 1);
+        assert_eq!(report.failed.len(), 1);
+    }
+
+    #[test]
+    fn trash_checks_every_path_first() {
+        let scratch = Scratch::new("trash");
+        let file = scratch.file("keep.txt", "keep");
+        let gone = scratch.0.join("gone.txt").display().to_string();
+
+        assert!(check_trash(&[]).is_ok());
+        assert!(check_trash(std::slice::from_ref(&file)).is_ok());
+        let err = check_trash(&[file.clone(), gone]).unwrap_err();
+        assert!(err.contains("no longer exists"));
+        assert!(Path::new(&file).exists());
+    }
+}
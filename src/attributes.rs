@@ -0,0 +1,83 @@
+// NTFS file attribute bits (as found in USN records) and their one-letter codes
+
+pub const FILE_ATTRIBUTE_READONLY: u32 = 0x00000001;
+pub const FILE_ATTRIBUTE_HIDDEN: u32 = 0x00000002;
+pub const FILE_ATTRIBUTE_SYSTEM: u32 = 0x00000004;
+pub const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x00000010; // A bitmask indicating a directory
+pub const FILE_ATTRIBUTE_ARCHIVE: u32 = 0x00000020;
+pub const FILE_ATTRIBUTE_TEMPORARY: u32 = 0x00000100;
+pub const FILE_ATTRIBUTE_SPARSE_FILE: u32 = 0x00000200;
+pub const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x00000400; // Symlinks, junctions, cloud placeholders
+pub const FILE_ATTRIBUTE_COMPRESSED: u32 = 0x00000800;
+pub const FILE_ATTRIBUTE_OFFLINE: u32 = 0x00001000;
+pub const FILE_ATTRIBUTE_NOT_CONTENT_INDEXED: u32 = 0x00002000;
+pub const FILE_ATTRIBUTE_ENCRYPTED: u32 = 0x00004000;
+
+// Letter codes, in the order they are displayed. Same letters as Explorer/attrib.exe where they exist
+const LETTERS: &[(char, u32, &str)] = &[
+    ('R', FILE_ATTRIBUTE_READONLY, "readonly"),
+    ('H', FILE_ATTRIBUTE_HIDDEN, "hidden"),
+    ('S', FILE_ATTRIBUTE_SYSTEM, "system"),
+    ('D', FILE_ATTRIBUTE_DIRECTORY, "directory"),
+    ('A', FILE_ATTRIBUTE_ARCHIVE, "archive"),
+    ('T', FILE_ATTRIBUTE_TEMPORARY, "temporary"),
+    ('P', FILE_ATTRIBUTE_SPARSE_FILE, "sparse"),
+    ('L', FILE_ATTRIBUTE_REPARSE_POINT, "reparse"),
+    ('C', FILE_ATTRIBUTE_COMPRESSED, "compressed"),
+    ('O', FILE_ATTRIBUTE_OFFLINE, "offline"),
+    ('I', FILE_ATTRIBUTE_NOT_CONTENT_INDEXED, "notindexed"),
+    ('E', FILE_ATTRIBUTE_ENCRYPTED, "encrypted"),
+];
+
+// Attributes worth flagging in a result row. Archive/not-indexed are on most files and just add noise
+const NOTABLE: u32 = FILE_ATTRIBUTE_READONLY
+    | FILE_ATTRIBUTE_HIDDEN
+    | FILE_ATTRIBUTE_SYSTEM
+    | FILE_ATTRIBUTE_REPARSE_POINT
+    | FILE_ATTRIBUTE_COMPRESSED
+    | FILE_ATTRIBUTE_OFFLINE
+    | FILE_ATTRIBUTE_ENCRYPTED;
+
+// Parse "HS", "hidden" or "hidden;system" into a bitmask
+pub fn parse_attributes(value: &str) -> Result<u32, String> {
+    let mut mask = 0;
+    for part in value.split(';').filter(|p| !p.is_empty()) {
+        let lower = part.to_lowercase();
+        if let Some(&(_, bit, _)) = LETTERS.iter().find(|(_, _, word)| *word == lower) {
+            mask |= bit;
+            continue;
+        }
+        for c in part.chars() {
+            let c = c.to_ascii_uppercase();
+            let &(_, bit, _) = LETTERS.iter()
+                .find(|(letter, _, _)| *letter == c)
+                .ok_or_else(|| format!("Unknown attribute '{}'", c))?;
+            mask |= bit;
+        }
+    }
+    Ok(mask)
+}
+
+// Letters for every attribute set in `attributes`, e.g. "RHS"
+pub fn attribute_letters(attributes: u32) -> String {
+    LETTERS.iter()
+        .filter(|(_, bit, _)| attributes & bit != 0)
+        .map(|(letter, _, _)| *letter)
+        .collect()
+}
+
+// The names of the same, e.g. ["readonly", "hidden", "system"]
+pub fn attribute_names(attributes: u32) -> Vec<&'static str> {
+    LETTERS.iter()
+        .filter(|(_, bit, _)| attributes & bit != 0)
+        .map(|(_, _, name)| *name)
+        .collect()
+}
+
+pub fn notable_letters(attributes: u32) -> String {
+    attribute_letters(attributes & NOTABLE)
+}
+
+pub fn is_hidden_or_system(attributes: u32) -> bool {
+    attributes & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0
+}
@@ -0,0 +1,112 @@
+// Offline catalogs: a saved copy of one drive's index, so a USB disk or card stays
+// searchable after it is unplugged. Catalogs are keyed by volume serial number, which
+// survives the drive letter changing between plug-ins, and live in the data directory as
+//
+//   catalogs/<serial>.toml   name, label and when it was saved
+//   catalogs/<serial>.efu    the entries, paths relative to the drive root
+//
+// While the volume is mounted the live scan covers it and the catalog is refreshed from
+// that scan; otherwise the catalog is loaded as a virtual drive named after it.
+
+use crate::efu;
+use crate::FileEntry;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Catalog {
+    pub name: String,
+    pub label: String, // Volume label when it was saved
+    pub serial: u32,
+    pub saved: i64, // Unix seconds
+    pub entries: usize,
+}
+
+impl Catalog {
+    // The drive name its entries get while it is offline, e.g. "[Backup disk]"
+    pub fn drive_name(&self) -> String {
+        format!("[{}]", self.name)
+    }
+
+    // Serials are shown the way `vol` prints them, e.g. 1A2B-3C4D
+    pub fn serial_text(&self) -> String {
+        format!("{:04X}-{:04X}", self.serial >> 16, self.serial & 0xFFFF)
+    }
+
+    pub fn list_path(&self) -> Option<PathBuf> {
+        catalog_dir().map(|dir| dir.join(format!("{:08X}.efu", self.serial)))
+    }
+
+    fn meta_path(&self) -> Option<PathBuf> {
+        catalog_dir().map(|dir| dir.join(format!("{:08X}.toml", self.serial)))
+    }
+
+    pub fn delete(&self) -> Result<(), String> {
+        for path in [self.meta_path(), self.list_path()].into_iter().flatten() {
+            if path.exists() {
+                std::fs::remove_file(&path).map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn catalog_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("Deep Search").join("catalogs"))
+}
+
+// Every saved catalog, by name. A missing directory just means none.
+pub fn load_all() -> Result<Vec<Catalog>, String> {
+    let Some(dir) = catalog_dir() else {
+        return Ok(Vec::new());
+    };
+    let Ok(listing) = std::fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+
+    let mut catalogs = Vec::new();
+    for item in listing.flatten() {
+        let path = item.path();
+        if path.extension().is_some_and(|e| e == "toml") {
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let catalog: Catalog = toml::from_str(&text)
+                .map_err(|e| format!("Invalid catalog {}: {}", path.display(), e))?;
+            catalogs.push(catalog);
+        }
+    }
+    catalogs.sort_by_key(|c| c.name.to_lowercase());
+    Ok(catalogs)
+}
+
+// Save (or overwrite) the catalog of a mounted drive from the current index
+pub fn save(
+    name: &str,
+    serial: u32,
+    label: &str,
+    drive_idx: u8,
+    data: &[FileEntry],
+    drives: &[String],
+) -> Result<Catalog, String> {
+    let dir = catalog_dir().ok_or("No data directory to save catalogs in")?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let mut catalog = Catalog {
+        name: name.to_string(),
+        label: label.to_string(),
+        serial,
+        saved: chrono::Utc::now().timestamp(),
+        entries: 0,
+    };
+
+    let list_path = catalog.list_path().ok_or("No data directory to save catalogs in")?;
+    let file = std::fs::File::create(&list_path)
+        .map_err(|e| format!("Failed to create {}: {}", list_path.display(), e))?;
+    catalog.entries = efu::export_drive(std::io::BufWriter::new(file), data, drives, drive_idx)
+        .map_err(|e| format!("Failed to write {}: {}", list_path.display(), e))?;
+
+    let meta_path = catalog.meta_path().ok_or("No data directory to save catalogs in")?;
+    let text = toml::to_string_pretty(&catalog).map_err(|e| format!("Failed to serialize catalog: {}", e))?;
+    std::fs::write(&meta_path, text).map_err(|e| format!("Failed to write {}: {}", meta_path.display(), e))?;
+    Ok(catalog)
+}
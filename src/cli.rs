@@ -0,0 +1,317 @@
+// Command-line mode, for scripts and terminals:
+//
+//   deep_search search <query> [--regex] [--limit N] [--json] [--sort KEY] [--reverse] [--all]
+//   deep_search run <saved search> [same options]
+//   deep_search export-index <file.efu>
+//   deep_search --stdio                    (JSON-RPC for editors and launchers, see rpc.rs)
+//
+// Asks a running Deep Search for the results over IPC (see ipc.rs) and otherwise scans
+// the drives like the GUI does. Prints full paths (or JSON lines) to stdout. Exit codes:
+// 0 when something matched, 1 when nothing did, 2 on errors.
+
+use crate::efu;
+use crate::exclude::ExclusionSet;
+use crate::filetype::all_categories;
+use crate::format::entry_json;
+use crate::index::{IndexSnapshot, SearchRequest};
+use crate::ipc;
+use crate::query::{MatchMode, Query};
+use crate::saved;
+use crate::settings::Settings;
+use crate::sort::SortKey;
+use crate::{resolve_path, scan_all_drives};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+// Exit codes, as grep has them: 0 for success (for `search`, that something matched),
+// 1 when a search matched nothing, 2 for an error of any kind
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_NO_MATCHES: i32 = 1;
+pub const EXIT_ERROR: i32 = 2;
+
+const USAGE: &str = "\
+Usage: deep_search search <query> [options]
+
+Options:
+  --regex        Treat the name part of the query as a regular expression
+  --limit N      Print at most N results
+  --json         Print one JSON object per line instead of plain paths
+  --sort KEY     Sort by name, path, size, modified or created
+                 (size and dates sort largest/newest first)
+  --reverse      Reverse the sort order
+  --all          Include hidden/system files and excluded items
+  --scan         Scan the drives even when Deep Search is already running
+
+The query uses the same syntax as the search bar, e.g. \"report ext:pdf dm:thisyear\".";
+
+const RUN_USAGE: &str = "\
+Usage: deep_search run <name> [options]
+
+Runs a saved search (Saved in the app, or saved_searches in settings.toml). Takes the
+same options as `deep_search search`; --sort, --reverse, --regex and --all change the
+saved search for this run only.";
+
+const EXPORT_USAGE: &str = "\
+Usage: deep_search export-index <file.efu>
+
+Scans the drives and writes every file and folder on them to an EFU file list, which
+can be loaded as a virtual drive on another machine (File Lists… in the app).";
+
+struct SearchArgs {
+    request: SearchRequest,
+    json: bool,
+    scan: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<SearchArgs, String> {
+    let mut words = Vec::new();
+    let mut parsed = SearchArgs {
+        request: SearchRequest::default(),
+        json: false,
+        scan: false,
+    };
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--regex" => parsed.request.mode = MatchMode::Regex,
+            "--json" => parsed.json = true,
+            "--reverse" => parsed.request.reverse = true,
+            "--all" => parsed.request.all = true,
+            "--scan" => parsed.scan = true,
+            "--limit" => {
+                let value = iter.next().ok_or("--limit needs a number")?;
+                parsed.request.count = Some(value.parse().map_err(|_| format!("Invalid --limit '{}'", value))?);
+            }
+            "--sort" => {
+                let value = iter.next().ok_or("--sort needs a key")?;
+                parsed.request.sort = Some(SortKey::parse(value).ok_or_else(|| format!("Unknown sort key '{}'", value))?);
+            }
+            // A lone "--" ends the options, so queries can start with a dash
+            "--" => words.extend(iter.by_ref().cloned()),
+            other if other.starts_with("--") => return Err(format!("Unknown option '{}'", other)),
+            other => words.push(other.to_string()),
+        }
+    }
+
+    parsed.request.query = words.join(" ");
+    Ok(parsed)
+}
+
+// The saved search named by the arguments, with any options given adjusting it
+fn saved_args(args: &[String]) -> Result<SearchArgs, String> {
+    let usage = |e: String| format!("{}\n\n{}", e, RUN_USAGE);
+    let mut parsed = parse_args(args).map_err(usage)?;
+    let name = parsed.request.query.trim();
+    if name.is_empty() {
+        return Err(usage("Missing saved search name".to_string()));
+    }
+
+    let settings = load_settings()?;
+    let Some(saved) = saved::find(&settings.saved_searches, name) else {
+        let names: Vec<&str> = settings.saved_searches.iter().map(|s| s.name.as_str()).collect();
+        let known = if names.is_empty() { "there are none yet".to_string() } else { names.join(", ") };
+        return Err(format!("No saved search named '{}' ({})", name, known));
+    };
+
+    let given = &parsed.request;
+    let mut request = saved.request();
+    request.count = given.count;
+    request.sort = given.sort.or(request.sort);
+    request.reverse ^= given.reverse;
+    request.all |= given.all;
+    if given.mode == MatchMode::Regex {
+        request.mode = MatchMode::Regex;
+    }
+    parsed.request = request;
+    Ok(parsed)
+}
+
+pub fn run_search(args: &[String]) -> i32 {
+    attach_console();
+
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{}", USAGE);
+        return EXIT_OK;
+    }
+
+    let parsed = parse_args(args).and_then(|parsed| {
+        if parsed.request.query.trim().is_empty() {
+            return Err("Missing search query".to_string());
+        }
+        Ok(parsed)
+    });
+    exit_code(parsed.map_err(|e| format!("{}\n\n{}", e, USAGE)).and_then(search))
+}
+
+pub fn run_saved(args: &[String]) -> i32 {
+    attach_console();
+
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{}", RUN_USAGE);
+        return EXIT_OK;
+    }
+
+    exit_code(saved_args(args).and_then(search))
+}
+
+fn exit_code(matches: Result<usize, String>) -> i32 {
+    match matches {
+        Ok(0) => EXIT_NO_MATCHES,
+        Ok(_) => EXIT_OK,
+        Err(e) => {
+            eprintln!("deep_search: {}", e);
+            EXIT_ERROR
+        }
+    }
+}
+
+// Returns the number of matches, however many of them were printed, so that `--limit 0`
+// still tells whether there are any
+fn search(args: SearchArgs) -> Result<usize, String> {
+    let mut out = BufWriter::new(std::io::stdout().lock());
+
+    // A running instance already has the index loaded, which saves the whole scan
+    if !args.scan {
+        let matches = ipc::query(&args.request, |result| {
+            let line = if args.json {
+                result.to_string()
+            } else {
+                result["path"].as_str().unwrap_or_default().to_string()
+            };
+            // A closed pipe (e.g. `| head`) just means the reader has seen enough
+            writeln!(out, "{}", line).is_ok()
+        })?;
+        if let Some(matches) = matches {
+            let _ = out.flush();
+            return Ok(matches);
+        }
+    }
+
+    let settings = load_settings()?;
+    let categories = all_categories(&settings.categories);
+
+    // Catch query mistakes before spending seconds on a scan
+    Query::parse(&args.request.query, args.request.mode, &categories)?;
+
+    let (index, errors) = scan_index(&settings)?;
+    for error in &errors {
+        eprintln!("deep_search: warning: {}", error);
+    }
+
+    let (total, results) = index.run(&args.request)?;
+    for entry in &results {
+        let path = resolve_path(entry, &index.data, &index.drives);
+        let line = if args.json {
+            entry_json(entry, &path).to_string()
+        } else {
+            path
+        };
+
+        if writeln!(out, "{}", line).is_err() {
+            break;
+        }
+    }
+    let _ = out.flush();
+
+    Ok(total)
+}
+
+// Settings for the modes that run without the window. Like the window, they go on with
+// the default in place of a value out of range, and say so.
+fn load_settings() -> Result<Settings, String> {
+    let (settings, problems) = Settings::load_repaired()?;
+    for problem in &problems {
+        eprintln!("deep_search: warning: {}", problem);
+    }
+    Ok(settings)
+}
+
+// Every setting at its default, as a starting point for settings.toml
+pub fn run_print_default_settings() -> i32 {
+    attach_console();
+    match toml::to_string_pretty(&Settings::default()) {
+        Ok(text) => {
+            print!("{}", text);
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("deep_search: {}", e);
+            EXIT_ERROR
+        }
+    }
+}
+
+// Scan the drives and build the index the way the app does, for the modes that run
+// without it. Also returns the warnings of drives that could not be read.
+pub fn scan_index(settings: &Settings) -> Result<(IndexSnapshot, Vec<String>), String> {
+    // Progress is only for the GUI; nobody listens here
+    let (tx_progress, _rx_progress) = crossbeam_channel::unbounded();
+    let scan = scan_all_drives(tx_progress, &settings.file_lists, &settings.scan)?;
+    let (data, drives, errors, folders) = (scan.entries, scan.drives, scan.errors, scan.folders);
+
+    let exclusions = ExclusionSet::build(&settings.exclude, &data, &drives, &folders)?;
+    let index = IndexSnapshot {
+        data: Arc::new(data),
+        drives: Arc::new(drives),
+        folders: Arc::new(folders),
+        categories: Arc::new(all_categories(&settings.categories)),
+        exclusions: Some(Arc::new(exclusions)),
+        ready: true,
+    };
+    Ok((index, errors))
+}
+
+pub fn run_export_index(args: &[String]) -> i32 {
+    attach_console();
+
+    let [path] = args else {
+        eprintln!("{}", EXPORT_USAGE);
+        return EXIT_ERROR;
+    };
+    if path == "--help" || path == "-h" {
+        println!("{}", EXPORT_USAGE);
+        return EXIT_OK;
+    }
+
+    match export_index(Path::new(path)) {
+        Ok(rows) => {
+            eprintln!("Wrote {} entries to {}", rows, path);
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("deep_search: {}", e);
+            EXIT_ERROR
+        }
+    }
+}
+
+fn export_index(path: &Path) -> Result<usize, String> {
+    // Other people's lists have no place in this machine's index
+    let settings = load_settings()?;
+    let (tx_progress, _rx_progress) = crossbeam_channel::unbounded();
+    let scan = scan_all_drives(tx_progress, &[], &settings.scan)?;
+    for error in &scan.errors {
+        eprintln!("deep_search: warning: {}", error);
+    }
+
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    efu::export_index(BufWriter::new(file), &scan.entries, &scan.drives)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+// Release builds use the GUI subsystem and start without a console. Borrow the one
+// from the terminal we were started from so output shows up there.
+#[cfg(windows)]
+fn attach_console() {
+    use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    unsafe {
+        let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+// Other systems keep the console of whatever started us
+#[cfg(not(windows))]
+fn attach_console() {}
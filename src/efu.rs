@@ -0,0 +1,222 @@
+// File lists in Everything's EFU layout, a CSV file with the columns
+//
+//   Filename,Size,Date Modified,Date Created,Attributes
+//
+// where Filename is a full path, dates are FILETIMEs (100ns ticks since 1601) and
+// Attributes is the FILE_ATTRIBUTE_* value in decimal. Used to ship the index of one
+// machine to another: export_index writes one, load_list reads one back as a read-only
+// "virtual drive" that is searched next to the live volumes. Offline catalogs (see
+// catalog.rs) use the same format for a single drive.
+
+use crate::attributes::FILE_ATTRIBUTE_DIRECTORY;
+use crate::{resolve_path, FileEntry};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+const HEADER: &str = "Filename,Size,Date Modified,Date Created,Attributes";
+
+// Seconds between 1601-01-01 and 1970-01-01
+const FILETIME_UNIX_OFFSET: i64 = 11_644_473_600;
+const FILETIME_TICKS_PER_SECOND: i64 = 10_000_000;
+
+// Virtual drives are named after their list in brackets, e.g. "[buildbox]". Live drives
+// are always "X:", so the two can't be confused and the name also reads well in paths.
+pub fn virtual_drive_name(list: &Path) -> String {
+    let stem = list.file_stem().map_or("list".into(), |s| s.to_string_lossy());
+    format!("[{}]", stem)
+}
+
+pub fn is_virtual_drive(drive: &str) -> bool {
+    drive.starts_with('[')
+}
+
+// Write every entry on the live drives, parents before children. Returns the row count.
+pub fn export_index<W: Write>(out: W, data: &[FileEntry], drives: &[String]) -> io::Result<usize> {
+    let entries = data.iter()
+        .filter(|e| drives.get(e.drive_idx as usize).is_some_and(|d| !is_virtual_drive(d)));
+    write_list(out, entries, |e| resolve_path(e, data, drives))
+}
+
+// Write one drive with paths relative to its root, so the list still fits when the drive
+// comes back under another letter
+pub fn export_drive<W: Write>(out: W, data: &[FileEntry], drives: &[String], drive_idx: u8) -> io::Result<usize> {
+    let root = format!("{}\\", drives.get(drive_idx as usize).map_or("", |d| d.as_str()));
+    let entries = data.iter().filter(|e| e.drive_idx == drive_idx);
+    write_list(out, entries, |e| {
+        let path = resolve_path(e, data, drives);
+        path.strip_prefix(&root).map(str::to_string).unwrap_or(path)
+    })
+}
+
+fn write_list<'a, W: Write>(
+    mut out: W,
+    entries: impl Iterator<Item = &'a FileEntry>,
+    path_of: impl Fn(&FileEntry) -> String,
+) -> io::Result<usize> {
+    // Volume roots have no name of their own
+    let mut ordered: Vec<&FileEntry> = entries.filter(|e| e.parent_id != e.id).collect();
+    ordered.sort_unstable_by_key(|e| e.order);
+
+    writeln!(out, "{}", HEADER)?;
+    for entry in &ordered {
+        let size = if entry.is_dir { String::new() } else { entry.size.to_string() };
+        writeln!(
+            out,
+            "\"{}\",{},{},{},{}",
+            path_of(entry).replace('"', "\"\""),
+            size,
+            to_filetime(entry.modified),
+            to_filetime(entry.created),
+            entry.attributes
+        )?;
+    }
+    out.flush()?;
+    Ok(ordered.len())
+}
+
+// Read a list into entries for `drive_idx`. Folders that only appear as part of a path are
+// created along the way, so lists without folder rows still have a full tree. Order and
+// subtree_end are left for number_subtrees.
+pub fn load_list(path: &Path, drive_idx: u8) -> Result<Vec<FileEntry>, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut lines = io::BufReader::new(file).lines();
+    let failed = |e: io::Error| format!("Failed to read {}: {}", path.display(), e);
+
+    let header = lines.next().transpose().map_err(failed)?.unwrap_or_default();
+    let columns: Vec<String> = split_csv(header.trim_start_matches('\u{feff}'))
+        .iter()
+        .map(|c| c.trim().to_lowercase())
+        .collect();
+    let column = |name: &str| columns.iter().position(|c| c == name);
+    let filename = column("filename").ok_or_else(|| format!("{} has no Filename column", path.display()))?;
+    let (size, modified, created, attributes) =
+        (column("size"), column("date modified"), column("date created"), column("attributes"));
+
+    let mut list = ListBuilder::new(drive_idx);
+    for (number, line) in lines.enumerate() {
+        let line = line.map_err(failed)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = split_csv(&line);
+        let field = |idx: Option<usize>| idx.and_then(|i| fields.get(i)).map(|f| f.trim()).filter(|f| !f.is_empty());
+        let number_field = |idx: Option<usize>| -> Result<i64, String> {
+            field(idx).map_or(Ok(0), |f| {
+                f.parse().map_err(|_| format!("{} line {}: invalid number '{}'", path.display(), number + 2, f))
+            })
+        };
+
+        let Some(name) = field(Some(filename)) else { continue };
+        let attributes = number_field(attributes)? as u32;
+        list.add(
+            name,
+            attributes & FILE_ATTRIBUTE_DIRECTORY != 0,
+            number_field(size)?.max(0) as u64,
+            from_filetime(number_field(modified)?),
+            from_filetime(number_field(created)?),
+            attributes,
+        );
+    }
+    Ok(list.entries)
+}
+
+// Turns paths into a tree of entries, also used by IndexBuilder
+pub(crate) struct ListBuilder {
+    drive_idx: u8,
+    pub(crate) entries: Vec<FileEntry>,
+    // (parent id, lowercase name) -> index into entries
+    by_name: HashMap<(u64, String), usize>,
+}
+
+// Top-level entries point at this id, which is never used, so they count as roots
+const ROOT_ID: u64 = 0;
+
+impl ListBuilder {
+    pub(crate) fn new(drive_idx: u8) -> Self {
+        Self { drive_idx, entries: Vec::new(), by_name: HashMap::new() }
+    }
+
+    pub(crate) fn add(&mut self, path: &str, is_dir: bool, size: u64, modified: i64, created: i64, attributes: u32) {
+        let parts: Vec<&str> = path.split(['\\', '/']).filter(|p| !p.is_empty()).collect();
+        let Some((last, folders)) = parts.split_last() else { return };
+
+        let mut parent = ROOT_ID;
+        for folder in folders {
+            let idx = self.child(parent, folder, true);
+            parent = self.entries[idx].id;
+        }
+
+        let idx = self.child(parent, last, is_dir);
+        let entry = &mut self.entries[idx];
+        entry.is_dir |= is_dir;
+        entry.size = if entry.is_dir { 0 } else { size };
+        entry.modified = modified;
+        entry.created = created;
+        entry.attributes = attributes | if entry.is_dir { FILE_ATTRIBUTE_DIRECTORY } else { 0 };
+    }
+
+    // The existing entry with this name, or a new bare one
+    fn child(&mut self, parent: u64, name: &str, is_dir: bool) -> usize {
+        let key = (parent, name.to_lowercase());
+        if let Some(&idx) = self.by_name.get(&key) {
+            // A name seen as a file first can turn out to be a folder later on
+            self.entries[idx].is_dir |= is_dir;
+            return idx;
+        }
+
+        let idx = self.entries.len();
+        self.entries.push(FileEntry {
+            id: idx as u64 + 1,
+            parent_id: parent,
+            name: name.to_string(),
+            is_dir,
+            drive_idx: self.drive_idx,
+            size: 0,
+            modified: 0,
+            created: 0,
+            attributes: if is_dir { FILE_ATTRIBUTE_DIRECTORY } else { 0 },
+            order: 0,
+            subtree_end: 0,
+        });
+        self.by_name.insert(key, idx);
+        idx
+    }
+}
+
+// Unknown times are 0 on both sides
+fn to_filetime(unix: i64) -> String {
+    if unix == 0 {
+        return String::new();
+    }
+    ((unix + FILETIME_UNIX_OFFSET) * FILETIME_TICKS_PER_SECOND).to_string()
+}
+
+fn from_filetime(filetime: i64) -> i64 {
+    if filetime <= 0 {
+        return 0;
+    }
+    filetime / FILETIME_TICKS_PER_SECOND - FILETIME_UNIX_OFFSET
+}
+
+// One CSV line into fields, with "" as an escaped quote inside quoted fields
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
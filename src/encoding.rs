@@ -0,0 +1,136 @@
+// Telling text from binary data and decoding it without being told the encoding, as
+// the preview pane needs for whatever file it is shown.
+
+// The text in `bytes` and the encoding it was read as, or None for binary data.
+// A byte order mark decides it when there is one; otherwise it is UTF-16 if it looks
+// like that, UTF-8 if it decodes as that, and the Windows ANSI code page if not. The
+// last character may have been cut in half by a size limit, that is not an encoding error.
+pub fn decode_text(bytes: &[u8]) -> Option<(String, &'static str)> {
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return Some((utf8_prefix(rest), "UTF-8 with BOM"));
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return Some((utf16(rest, u16::from_le_bytes), "UTF-16 LE"));
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        return Some((utf16(rest, u16::from_be_bytes), "UTF-16 BE"));
+    }
+    if let Some((unit, encoding)) = utf16_without_bom(bytes) {
+        return Some((utf16(bytes, unit), encoding));
+    }
+    // Text files don't have NULs, nearly every binary format does
+    if bytes.contains(&0) {
+        return None;
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Some((text.to_string(), if text.is_ascii() { "ASCII" } else { "UTF-8" })),
+        Err(e) if e.error_len().is_none() => Some((utf8_prefix(bytes), "UTF-8")),
+        Err(_) => Some((bytes.iter().map(|&b| windows_1252(b)).collect(), "Windows-1252")),
+    }
+}
+
+// UTF-16 as some Windows tools write it, without a byte order mark. Mostly Latin text
+// then has a NUL as the same half of nearly every pair, and no control characters
+// once decoded, which binary data with lots of NULs rarely manages.
+fn utf16_without_bom(bytes: &[u8]) -> Option<(Utf16Unit, &'static str)> {
+    let pairs = bytes.len() / 2;
+    if pairs < 2 {
+        return None;
+    }
+    let zeros = |half: usize| bytes.chunks_exact(2).filter(|pair| pair[half] == 0).count();
+    let (first, second) = (zeros(0), zeros(1));
+    let (unit, encoding): (Utf16Unit, _) = if second * 10 >= pairs * 9 && first * 10 < pairs {
+        (u16::from_le_bytes, "UTF-16 LE")
+    } else if first * 10 >= pairs * 9 && second * 10 < pairs {
+        (u16::from_be_bytes, "UTF-16 BE")
+    } else {
+        return None;
+    };
+
+    let text = utf16(bytes, unit);
+    let plain = text.chars().all(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r' | '\x0C'));
+    plain.then_some((unit, encoding))
+}
+
+// UTF-8 that may stop mid-character
+fn utf8_prefix(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(e) if e.error_len().is_none() => String::from_utf8_lossy(&bytes[..e.valid_up_to()]).into_owned(),
+        Err(_) => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+// Reads a UTF-16 code unit in one byte order
+type Utf16Unit = fn([u8; 2]) -> u16;
+
+fn utf16(bytes: &[u8], unit: Utf16Unit) -> String {
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]])).collect();
+    let mut text = String::from_utf16_lossy(&units);
+    // A surrogate pair cut in half by the size limit
+    if text.ends_with(char::REPLACEMENT_CHARACTER) && units.last().is_some_and(|u| (0xD800..0xDC00).contains(u)) {
+        text.pop();
+    }
+    text
+}
+
+// Code page 1252 is Latin-1 apart from 0x80-0x9F
+fn windows_1252(byte: u8) -> char {
+    const HIGH: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+        '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+    ];
+    match byte {
+        0x80..=0x9F => HIGH[(byte - 0x80) as usize],
+        _ => byte as char,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(text: &str, unit: fn(u16) -> [u8; 2]) -> Vec<u8> {
+        text.encode_utf16().flat_map(unit).collect()
+    }
+
+    #[test]
+    fn decode_text_finds_utf16_without_a_bom() {
+        let text = "Name,Size\r\nreport.pdf,1200\r\n";
+        assert_eq!(decode_text(&encode(text, u16::to_le_bytes)), Some((text.to_string(), "UTF-16 LE")));
+        assert_eq!(decode_text(&encode(text, u16::to_be_bytes)), Some((text.to_string(), "UTF-16 BE")));
+        // Cut off after the first byte of a pair by the size limit
+        let mut cut = encode(text, u16::to_le_bytes);
+        cut.pop();
+        assert_eq!(decode_text(&cut).map(|(_, encoding)| encoding), Some("UTF-16 LE"));
+    }
+
+    #[test]
+    fn decode_text_keeps_telling_text_from_binary() {
+        assert_eq!(decode_text(b"plain"), Some(("plain".to_string(), "ASCII")));
+        assert_eq!(decode_text("caf\u{e9}".as_bytes()), Some(("caf\u{e9}".to_string(), "UTF-8")));
+        assert_eq!(decode_text(b"caf\xe9 au lait"), Some(("caf\u{e9} au lait".to_string(), "Windows-1252")));
+        // Small integers: every other byte NUL, but the rest are control characters
+        assert_eq!(decode_text(&[1, 0, 2, 0, 3, 0, 4, 0, 5, 0]), None);
+        assert_eq!(decode_text(b"MZ\x90\x00\x03\x00\x00\x00"), None);
+    }
+
+    #[test]
+    fn decode_text_drops_a_byte_left_over_from_utf16() {
+        assert_eq!(decode_text(&[0xFF, 0xFE, b'a', 0, b'b', 0, b'c']), Some(("ab".to_string(), "UTF-16 LE")));
+        assert_eq!(decode_text(&[0xFE, 0xFF, 0, b'a', 0]), Some(("a".to_string(), "UTF-16 BE")));
+        assert_eq!(decode_text(&[b'a', 0, b'b', 0, b'c']), Some(("ab".to_string(), "UTF-16 LE")));
+        // Too short to tell, so it is taken for what it is
+        assert_eq!(decode_text(b"abc"), Some(("abc".to_string(), "ASCII")));
+        assert_eq!(decode_text(&[b'a', 0, b'b']), None);
+    }
+
+    #[test]
+    fn decode_text_reads_latin1_as_windows_1252() {
+        // Mostly ASCII with a few bytes that aren't UTF-8, and no NULs: never UTF-16
+        let text = b"Gr\xfc\xdfe aus K\xf6ln, \x93Stra\xdfe\x94 \x80 5";
+        assert_eq!(decode_text(text), Some(("Grüße aus Köln, “Straße” € 5".to_string(), "Windows-1252")));
+        assert_eq!(decode_text(b"na\xefve r\xe9sum\xe9 with an odd length"), Some(("naïve résumé with an odd length".to_string(), "Windows-1252")));
+    }
+}
@@ -0,0 +1,386 @@
+// The public search API: Index, Search and Hit. A thin layer over the same FileEntry
+// data, query matching and sorting the app uses, for calling the engine from Rust.
+
+use crate::efu::ListBuilder;
+use crate::query::Query;
+use crate::sort::{sort_results, SortKey};
+use crate::{number_subtrees, resolve_path, FileEntry, FolderTree};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The entries of one or more drives, held in memory and ready to search.
+///
+/// Cloning is cheap: the entries are shared, not copied.
+#[derive(Clone, Debug, Default)]
+pub struct Index {
+    data: Arc<Vec<FileEntry>>,
+    drives: Arc<Vec<String>>,
+    folders: Arc<FolderTree>,
+}
+
+impl Index {
+    /// Builds an index from entries in any order, e.g. straight from a scan. `drives`
+    /// names each `drive_idx`, e.g. `["C:", "D:"]`.
+    pub fn new(mut entries: Vec<FileEntry>, drives: Vec<String>) -> Index {
+        entries.par_sort_unstable_by(|a, b| a.drive_idx.cmp(&b.drive_idx).then(a.id.cmp(&b.id)));
+        let mut children: HashMap<(u8, u64), Vec<usize>> = HashMap::new();
+        for (i, e) in entries.iter().enumerate() {
+            children.entry((e.drive_idx, e.parent_id)).or_default().push(i);
+        }
+        number_subtrees(&mut entries, &children);
+        let folders = Arc::new(FolderTree::new(&entries));
+        Index { data: Arc::new(entries), drives: Arc::new(drives), folders }
+    }
+
+    /// Wraps entries that are already sorted by `(drive_idx, id)` and numbered, as the
+    /// app keeps them, along with the [`FolderTree`] built from them. Nothing is copied.
+    pub fn from_shared(data: Arc<Vec<FileEntry>>, drives: Arc<Vec<String>>, folders: Arc<FolderTree>) -> Index {
+        Index { data, drives, folders }
+    }
+
+    /// Starts an index built from paths, handy for tests and fixtures.
+    pub fn builder() -> IndexBuilder {
+        IndexBuilder::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn drives(&self) -> &[String] {
+        &self.drives
+    }
+
+    pub fn entries(&self) -> &[FileEntry] {
+        &self.data
+    }
+
+    /// Looks up the query's `parent:` folders. [`Index::search`] does this itself and
+    /// lets a missing folder match nothing; call this first to report it instead.
+    ///
+    /// ```
+    /// # use deep_search::{Index, query::{MatchMode, Query}};
+    /// let index = Index::builder().file(r"C:\src\main.rs", 1_000, 0).build();
+    /// let mut query = Query::parse(r"parent:C:\docs", MatchMode::Prefix, &[]).unwrap();
+    /// assert_eq!(index.resolve(&mut query).unwrap_err(), r"Folder not found: C:\docs");
+    /// ```
+    pub fn resolve(&self, query: &mut Query) -> Result<(), String> {
+        query.resolve(&self.data, &self.drives, &self.folders)
+    }
+
+    /// Every entry the query matches, in index order unless [`Search::sort`] says
+    /// otherwise. An empty query matches everything.
+    ///
+    /// Hits are produced as the iterator is advanced, so `take(n)` only scans as far as
+    /// the n-th match. Sorting has to see every match first, so a sorted search does
+    /// that (in parallel) on the first call to `next`.
+    ///
+    /// ```
+    /// # use deep_search::{Index, query::{MatchMode, Query}, sort::SortKey};
+    /// let index = Index::builder()
+    ///     .file(r"C:\logs\app.log", 5_000, 0)
+    ///     .file(r"C:\logs\app.1.log", 90_000, 0)
+    ///     .file(r"C:\logs\app.2.log", 40_000, 0)
+    ///     .build();
+    ///
+    /// // The second page of two, biggest first
+    /// let query = Query::parse("app", MatchMode::Prefix, &[]).unwrap();
+    /// let page: Vec<u64> = index.search(&query)
+    ///     .sort(SortKey::Size, true)
+    ///     .page(2, 2)
+    ///     .filter_map(|hit| hit.size())
+    ///     .collect();
+    /// assert_eq!(page, [5_000]);
+    /// ```
+    pub fn search(&self, query: &Query) -> Search<'_> {
+        let mut query = query.clone();
+        // Missing folders stay unresolved and match nothing
+        let _ = query.resolve(&self.data, &self.drives, &self.folders);
+        Search {
+            index: self,
+            query,
+            sort: None,
+            offset: 0,
+            limit: None,
+            cancel: None,
+            reverse: false,
+            state: State::Pending,
+            returned: 0,
+        }
+    }
+}
+
+/// Builds an [`Index`] from full paths. The first part of each path is its drive, and
+/// folders along the way are created as needed.
+///
+/// ```
+/// # use deep_search::Index;
+/// let index = Index::builder()
+///     .dir(r"C:\empty")
+///     .file(r"C:\src\main.rs", 1_200, 1_700_000_000)
+///     .build();
+/// assert_eq!(index.drives(), ["C:"]);
+/// assert_eq!(index.len(), 3); // empty, src and main.rs
+/// ```
+#[derive(Default)]
+pub struct IndexBuilder {
+    drives: Vec<String>,
+    lists: Vec<ListBuilder>,
+}
+
+impl IndexBuilder {
+    /// A file with its size in bytes and modification time in Unix seconds (0 if unknown).
+    pub fn file(self, path: &str, size: u64, modified: i64) -> Self {
+        self.entry(path, false, size, modified, 0, 0)
+    }
+
+    pub fn dir(self, path: &str) -> Self {
+        self.entry(path, true, 0, 0, 0, 0)
+    }
+
+    /// An entry with every field given; `attributes` are `FILE_ATTRIBUTE_*` bits.
+    pub fn entry(mut self, path: &str, is_dir: bool, size: u64, modified: i64, created: i64, attributes: u32) -> Self {
+        let (drive, rest) = path.split_once(['\\', '/']).unwrap_or((path, ""));
+        let idx = match self.drives.iter().position(|d| d.eq_ignore_ascii_case(drive)) {
+            Some(idx) => idx,
+            None => {
+                self.drives.push(drive.to_string());
+                self.lists.push(ListBuilder::new(self.lists.len() as u8));
+                self.lists.len() - 1
+            }
+        };
+        self.lists[idx].add(rest, is_dir, size, modified, created, attributes);
+        self
+    }
+
+    pub fn build(self) -> Index {
+        let entries = self.lists.into_iter().flat_map(|list| list.entries).collect();
+        Index::new(entries, self.drives)
+    }
+}
+
+/// Stops a running [`Search`] from another thread. Clones share the same flag.
+///
+/// ```
+/// # use deep_search::{CancelToken, Index, query::Query};
+/// let index = Index::builder().file(r"C:\a.txt", 1, 0).file(r"C:\b.txt", 1, 0).build();
+/// let cancel = CancelToken::new();
+/// let mut hits = index.search(&Query::default()).cancel_with(&cancel);
+///
+/// assert!(hits.next().is_some());
+/// cancel.cancel();
+/// assert!(hits.next().is_none());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A search in progress, returned by [`Index::search`]. Iterate it for the [`Hit`]s.
+pub struct Search<'a> {
+    index: &'a Index,
+    query: Query,
+    sort: Option<(SortKey, bool)>,
+    offset: usize,
+    limit: Option<usize>,
+    cancel: Option<CancelToken>,
+    reverse: bool,
+    state: State<'a>,
+    returned: usize,
+}
+
+enum State<'a> {
+    Pending,
+    // Walking the index; the position of the next entry to test
+    Scanning(usize),
+    // Every match, already sorted and paged
+    Sorted(std::vec::IntoIter<&'a FileEntry>),
+}
+
+impl<'a> Search<'a> {
+    /// Orders the hits by `key`, biggest/newest/last first if `descending`.
+    /// [`SortKey::descending_by_default`] says which way people usually want it.
+    pub fn sort(mut self, key: SortKey, descending: bool) -> Self {
+        self.sort = Some((key, descending));
+        self
+    }
+
+    /// Reverses the order of the hits: index order backwards, or the other direction of
+    /// the sort.
+    pub fn reverse(mut self) -> Self {
+        self.reverse = !self.reverse;
+        self
+    }
+
+    /// Skips the first `offset` hits and stops after `count` more.
+    pub fn page(mut self, offset: usize, count: usize) -> Self {
+        self.offset = offset;
+        self.limit = Some(count);
+        self
+    }
+
+    /// Ends the search early once `token` is cancelled.
+    pub fn cancel_with(mut self, token: &CancelToken) -> Self {
+        self.cancel = Some(token.clone());
+        self
+    }
+
+    /// Counts every hit, ignoring the page, in parallel and without collecting them.
+    /// Gives a partial count if cancelled along the way.
+    ///
+    /// ```
+    /// # use deep_search::{Index, query::{MatchMode, Query}};
+    /// let index = Index::builder().file(r"C:\a1.txt", 1, 0).file(r"C:\a2.txt", 1, 0).build();
+    /// let query = Query::parse("a", MatchMode::Prefix, &[]).unwrap();
+    /// let search = index.search(&query).page(0, 1);
+    /// assert_eq!(search.total(), 2);
+    /// assert_eq!(search.count(), 1);
+    /// ```
+    pub fn total(&self) -> usize {
+        let (query, cancel) = (&self.query, &self.cancel);
+        self.index.data.par_iter()
+            .filter(|e| !cancel.as_ref().is_some_and(CancelToken::is_cancelled) && query.matches(e))
+            .count()
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+
+    fn start(&mut self) {
+        let Some((key, descending)) = self.sort else {
+            self.state = State::Scanning(0);
+            return;
+        };
+
+        let data: &'a [FileEntry] = &self.index.data;
+        let (query, cancel) = (&self.query, &self.cancel);
+        let mut matches: Vec<&'a FileEntry> = data.par_iter()
+            .filter(|e| !cancel.as_ref().is_some_and(CancelToken::is_cancelled) && query.matches(e))
+            .collect();
+        sort_results(&mut matches, key, descending != self.reverse, data, &self.index.drives);
+        matches.drain(..self.offset.min(matches.len()));
+        self.state = State::Sorted(matches.into_iter());
+    }
+}
+
+impl<'a> Iterator for Search<'a> {
+    type Item = Hit<'a>;
+
+    fn next(&mut self) -> Option<Hit<'a>> {
+        if self.limit.is_some_and(|limit| self.returned >= limit) || self.is_cancelled() {
+            return None;
+        }
+        if matches!(self.state, State::Pending) {
+            self.start();
+        }
+
+        let data: &'a [FileEntry] = &self.index.data;
+        let entry = match &mut self.state {
+            State::Scanning(pos) => loop {
+                if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+                    return None;
+                }
+                let i = if self.reverse { data.len().checked_sub(*pos + 1)? } else { *pos };
+                let entry = data.get(i)?;
+                *pos += 1;
+                if self.query.matches(entry) {
+                    if self.offset > 0 {
+                        self.offset -= 1;
+                        continue;
+                    }
+                    break entry;
+                }
+            },
+            State::Sorted(hits) => hits.next()?,
+            State::Pending => unreachable!("started above"),
+        };
+
+        self.returned += 1;
+        Some(Hit { entry, index: self.index })
+    }
+}
+
+/// One search result. The path is only worked out when asked for.
+#[derive(Clone, Copy, Debug)]
+pub struct Hit<'a> {
+    entry: &'a FileEntry,
+    index: &'a Index,
+}
+
+impl<'a> Hit<'a> {
+    pub fn name(&self) -> &'a str {
+        &self.entry.name
+    }
+
+    /// The full path, e.g. `C:\src\main.rs`. Walks up the folder tree on every call.
+    pub fn path(&self) -> String {
+        resolve_path(self.entry, &self.index.data, &self.index.drives)
+    }
+
+    /// The drive (or virtual drive) the entry is on, e.g. `C:`
+    pub fn drive(&self) -> &'a str {
+        self.index.drives.get(self.entry.drive_idx as usize).map_or("", String::as_str)
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.entry.is_dir
+    }
+
+    /// Without the dot and as written in the name, so `JPG` and `jpg` both occur.
+    /// Empty for folders and files without one.
+    pub fn extension(&self) -> &'a str {
+        self.entry.ext()
+    }
+
+    /// Size in bytes as of the scan. None for folders.
+    pub fn size(&self) -> Option<u64> {
+        (!self.entry.is_dir).then_some(self.entry.size)
+    }
+
+    pub fn modified(&self) -> Option<SystemTime> {
+        system_time(self.entry.modified)
+    }
+
+    pub fn created(&self) -> Option<SystemTime> {
+        system_time(self.entry.created)
+    }
+
+    /// `FILE_ATTRIBUTE_*` bits, see [`crate::attributes`].
+    pub fn attributes(&self) -> u32 {
+        self.entry.attributes
+    }
+
+    /// Fresh metadata from the file system, for anything the index doesn't keep. Fails
+    /// for entries that aren't on this machine, e.g. from file lists.
+    pub fn metadata(&self) -> std::io::Result<std::fs::Metadata> {
+        std::fs::metadata(self.path())
+    }
+
+    pub fn entry(&self) -> &'a FileEntry {
+        self.entry
+    }
+}
+
+fn system_time(unix: i64) -> Option<SystemTime> {
+    (unix > 0).then(|| UNIX_EPOCH + Duration::from_secs(unix as u64))
+}
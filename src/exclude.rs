@@ -0,0 +1,174 @@
+// Exclusion rules for noisy locations ($Recycle.Bin, WinSxS, node_modules...)
+//
+// Rules are applied at query time so they can be switched off without a rescan. The
+// expensive part, working out which folders a rule hits, is done once per index/rule
+// change by ExclusionSet::build; after that each entry is a binary search on its order.
+
+use crate::attributes::parse_attributes;
+use crate::query::Scope;
+use crate::{find_entry, find_folder, FileEntry, FolderTree};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExcludeRules {
+    // Full folder paths, e.g. C:\Windows\WinSxS
+    pub folders: Vec<String>,
+    // Wildcards (* and ?) on names, e.g. node_modules or *.tmp. A pattern with '\' in it
+    // matches the end of the path instead, e.g. .git\objects
+    pub patterns: Vec<String>,
+    // Attribute letters; entries with any of them are excluded, e.g. "T" for temporary files
+    pub attributes: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExcludeSettings {
+    // Rules for every drive
+    #[serde(flatten)]
+    pub all: ExcludeRules,
+    // Extra rules for one drive, keyed by drive ("D:")
+    pub drives: BTreeMap<String, ExcludeRules>,
+}
+
+impl Default for ExcludeSettings {
+    fn default() -> Self {
+        Self {
+            all: ExcludeRules {
+                folders: Vec::new(),
+                patterns: ["$Recycle.Bin", "System Volume Information", "WinSxS", "node_modules", ".git\\objects"]
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect(),
+                attributes: String::new(),
+            },
+            drives: BTreeMap::new(),
+        }
+    }
+}
+
+impl ExcludeSettings {
+    // Every rule set that applies to the drive, global first
+    fn rules_for<'a>(&'a self, drive: &str) -> impl Iterator<Item = &'a ExcludeRules> + 'a {
+        let drive = drive.to_string();
+        std::iter::once(&self.all).chain(
+            self.drives.iter()
+                .filter(move |(d, _)| d.eq_ignore_ascii_case(&drive))
+                .map(|(_, rules)| rules),
+        )
+    }
+}
+
+// Everything the rules exclude, as merged half-open ranges of FileEntry::order values.
+// A folder's range covers its whole subtree.
+#[derive(Debug, Default)]
+pub struct ExclusionSet {
+    ranges: Vec<(u32, u32)>,
+}
+
+impl ExclusionSet {
+    pub fn build(settings: &ExcludeSettings, data: &[FileEntry], drives: &[String], folders: &FolderTree) -> Result<ExclusionSet, String> {
+        // Pre-parse per drive so the scan below does no string work beyond matching
+        let mut per_drive = Vec::with_capacity(drives.len());
+        for drive in drives {
+            let mut patterns: Vec<Vec<String>> = Vec::new();
+            let mut attributes = 0;
+            for rules in settings.rules_for(drive) {
+                patterns.extend(rules.patterns.iter()
+                    .map(|p| p.to_lowercase().split(['\\', '/']).filter(|c| !c.is_empty()).rev().map(str::to_string).collect::<Vec<_>>())
+                    .filter(|components| !components.is_empty()));
+                attributes |= parse_attributes(&rules.attributes)?;
+            }
+            per_drive.push((patterns, attributes));
+        }
+
+        let mut ranges: Vec<(u32, u32)> = data.par_iter()
+            .filter(|e| {
+                let Some((patterns, attributes)) = per_drive.get(e.drive_idx as usize) else {
+                    return false;
+                };
+                if e.attributes & attributes != 0 {
+                    return true;
+                }
+                if patterns.is_empty() {
+                    return false;
+                }
+                let name = e.name.to_lowercase();
+                patterns.iter().any(|p| wildcard_match(&p[0], &name) && matches_ancestors(&p[1..], e, data))
+            })
+            .map(|e| (e.order, e.subtree_end))
+            .collect();
+
+        // Folder rules carry their drive in the path, so per-drive sections need no special casing
+        let folder_rules = std::iter::once(&settings.all).chain(settings.drives.values()).flat_map(|r| &r.folders);
+        for folder in folder_rules {
+            match find_folder(folder, data, drives, folders) {
+                Some(Scope::Subtree { start, end }) => ranges.push((start, end)),
+                Some(Scope::Drive(drive_idx)) => {
+                    ranges.extend(data.iter().filter(|e| e.drive_idx == drive_idx).map(|e| (e.order, e.subtree_end)))
+                }
+                // Folders that don't exist (yet) just exclude nothing
+                Some(Scope::Unresolved(_)) | None => {}
+            }
+        }
+
+        ranges.par_sort_unstable();
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        Ok(ExclusionSet { ranges: merged })
+    }
+
+    pub fn contains(&self, entry: &FileEntry) -> bool {
+        let idx = self.ranges.partition_point(|&(start, _)| start <= entry.order);
+        idx > 0 && entry.order < self.ranges[idx - 1].1
+    }
+}
+
+// The rest of a path pattern (lowercase, innermost first) against the entry's parent,
+// grandparent and so on
+fn matches_ancestors(parts: &[String], entry: &FileEntry, data: &[FileEntry]) -> bool {
+    let mut current = entry;
+    for part in parts {
+        match find_entry(data, current.drive_idx, current.parent_id) {
+            Some(parent) if parent.id != current.id => current = parent,
+            _ => return false,
+        }
+        if !wildcard_match(part, &current.name.to_lowercase()) {
+            return false;
+        }
+    }
+    true
+}
+
+// Case-sensitive '*'/'?' matching; callers lowercase both sides
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None; // (pattern index after '*', text index it matched up to)
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi + 1, ti));
+            pi += 1;
+        } else if let Some((after_star, matched)) = star {
+            // Let the last '*' swallow one more character and retry
+            pi = after_star;
+            ti = matched + 1;
+            star = Some((after_star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
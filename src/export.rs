@@ -0,0 +1,293 @@
+// Writing result sets out as CSV, JSON, NDJSON or a plain list of paths. Rows go out
+// one at a time, so exporting a million results never builds the whole file in memory.
+
+use crate::attributes::attribute_letters;
+use crate::format::iso_time;
+use crate::{resolve_path, FileEntry, Search};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,   // One array of objects
+    Ndjson, // One object per line
+    Paths,  // One full path per line, columns are ignored
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 4] = [ExportFormat::Csv, ExportFormat::Json, ExportFormat::Ndjson, ExportFormat::Paths];
+
+    pub fn parse(value: &str) -> Option<ExportFormat> {
+        match value.to_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            "ndjson" | "jsonl" => Some(ExportFormat::Ndjson),
+            "paths" | "txt" => Some(ExportFormat::Paths),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Json => "JSON",
+            ExportFormat::Ndjson => "NDJSON",
+            ExportFormat::Paths => "Paths",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Paths => "txt",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json; charset=utf-8",
+            ExportFormat::Ndjson => "application/x-ndjson; charset=utf-8",
+            ExportFormat::Paths => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Column {
+    Name,
+    Path,
+    Extension,
+    Size,
+    Modified,
+    Created,
+    Attributes,
+}
+
+impl Column {
+    pub const ALL: [Column; 7] = [
+        Column::Name,
+        Column::Path,
+        Column::Extension,
+        Column::Size,
+        Column::Modified,
+        Column::Created,
+        Column::Attributes,
+    ];
+    pub const DEFAULT: [Column; 4] = [Column::Name, Column::Path, Column::Size, Column::Modified];
+
+    // The name used in CSV headers, JSON keys and the `columns` parameter
+    pub fn key(self) -> &'static str {
+        match self {
+            Column::Name => "name",
+            Column::Path => "path",
+            Column::Extension => "extension",
+            Column::Size => "size",
+            Column::Modified => "modified",
+            Column::Created => "created",
+            Column::Attributes => "attributes",
+        }
+    }
+
+    // Comma-separated keys, e.g. "name,path,size"
+    pub fn parse_list(value: &str) -> Result<Vec<Column>, String> {
+        value.split(',')
+            .map(str::trim)
+            .filter(|k| !k.is_empty())
+            .map(|k| {
+                Column::ALL.into_iter()
+                    .find(|c| c.key().eq_ignore_ascii_case(k))
+                    .ok_or_else(|| format!("Unknown column '{}'", k))
+            })
+            .collect()
+    }
+
+    // Unknown values (folder sizes, missing dates) are null in JSON and empty in CSV
+    fn value(self, entry: &FileEntry, path: &str) -> serde_json::Value {
+        match self {
+            Column::Name => entry.name.clone().into(),
+            Column::Path => path.into(),
+            Column::Extension => entry.ext().to_lowercase().into(),
+            Column::Size if entry.is_dir => serde_json::Value::Null,
+            Column::Size => entry.size.into(),
+            Column::Modified => iso_time(entry.modified).into(),
+            Column::Created => iso_time(entry.created).into(),
+            Column::Attributes => attribute_letters(entry.attributes).into(),
+        }
+    }
+}
+
+pub struct Exporter<W: Write> {
+    out: W,
+    format: ExportFormat,
+    columns: Vec<Column>,
+    rows: usize,
+}
+
+impl<W: Write> Exporter<W> {
+    // Writes the CSV header or the opening bracket straight away
+    pub fn new(mut out: W, format: ExportFormat, columns: &[Column]) -> io::Result<Self> {
+        match format {
+            ExportFormat::Csv => {
+                let header: Vec<&str> = columns.iter().map(|c| c.key()).collect();
+                out.write_all(header.join(",").as_bytes())?;
+                out.write_all(b"\r\n")?;
+            }
+            ExportFormat::Json => out.write_all(b"[")?,
+            ExportFormat::Ndjson | ExportFormat::Paths => {}
+        }
+        Ok(Self { out, format, columns: columns.to_vec(), rows: 0 })
+    }
+
+    pub fn write(&mut self, entry: &FileEntry, path: &str) -> io::Result<()> {
+        match self.format {
+            ExportFormat::Csv => {
+                for (i, column) in self.columns.iter().enumerate() {
+                    if i > 0 {
+                        self.out.write_all(b",")?;
+                    }
+                    let cell = match column.value(entry, path) {
+                        serde_json::Value::String(s) => csv_field(&s),
+                        serde_json::Value::Null => String::new(),
+                        other => other.to_string(),
+                    };
+                    self.out.write_all(cell.as_bytes())?;
+                }
+                self.out.write_all(b"\r\n")?;
+            }
+            ExportFormat::Json | ExportFormat::Ndjson => {
+                if self.format == ExportFormat::Json {
+                    self.out.write_all(if self.rows == 0 { b"\n" } else { b",\n" })?;
+                }
+                // Written by hand so the keys come out in column order
+                self.out.write_all(b"{")?;
+                for (i, column) in self.columns.iter().enumerate() {
+                    let separator = if i > 0 { "," } else { "" };
+                    write!(self.out, "{}\"{}\":{}", separator, column.key(), column.value(entry, path))?;
+                }
+                self.out.write_all(b"}")?;
+                if self.format == ExportFormat::Ndjson {
+                    self.out.write_all(b"\n")?;
+                }
+            }
+            ExportFormat::Paths => writeln!(self.out, "{}", path)?,
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    // Closes the JSON array and flushes. Returns the writer and the number of rows written.
+    pub fn finish(mut self) -> io::Result<(W, usize)> {
+        if self.format == ExportFormat::Json {
+            self.out.write_all(b"\n]\n")?;
+        }
+        self.out.flush()?;
+        Ok((self.out, self.rows))
+    }
+}
+
+// Export a result set in one go, e.g. to a file
+pub fn export_entries<W: Write>(
+    out: W,
+    entries: &[FileEntry],
+    format: ExportFormat,
+    columns: &[Column],
+    data: &[FileEntry],
+    drives: &[String],
+) -> io::Result<usize> {
+    let mut exporter = Exporter::new(out, format, columns)?;
+    for entry in entries {
+        exporter.write(entry, &resolve_path(entry, data, drives))?;
+    }
+    exporter.finish().map(|(_, rows)| rows)
+}
+
+// The same output as a Read, producing rows only as the reader asks for them. Used for
+// HTTP responses, which are pulled rather than pushed. The hits come straight from the
+// engine, so nothing but the row being written is held; no hits exports no rows.
+pub struct ExportReader<'a> {
+    exporter: Option<Exporter<Vec<u8>>>,
+    hits: Option<Search<'a>>,
+    pending: Vec<u8>,
+    pos: usize,
+}
+
+impl<'a> ExportReader<'a> {
+    pub fn new(hits: Option<Search<'a>>, format: ExportFormat, columns: &[Column]) -> io::Result<Self> {
+        Ok(Self {
+            exporter: Some(Exporter::new(Vec::new(), format, columns)?),
+            hits,
+            pending: Vec::new(),
+            pos: 0,
+        })
+    }
+}
+
+impl Read for ExportReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.pending.len() {
+            let Some(exporter) = &mut self.exporter else {
+                return Ok(0);
+            };
+            match self.hits.as_mut().and_then(Iterator::next) {
+                Some(hit) => {
+                    exporter.write(hit.entry(), &hit.path())?;
+                    self.pending = std::mem::take(&mut exporter.out);
+                }
+                None => {
+                    let exporter = self.exporter.take().expect("checked above");
+                    self.pending = exporter.finish()?.0;
+                }
+            }
+            self.pos = 0;
+        }
+
+        let n = buf.len().min(self.pending.len() - self.pos);
+        buf[..n].copy_from_slice(&self.pending[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+// Quote a CSV field if it needs it (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::{MatchMode, Query};
+    use crate::Index;
+
+    #[test]
+    fn reader_writes_the_hits_it_pulls() {
+        let index = Index::builder()
+            .file(r"C:\logs\app.log", 5_000, 0)
+            .file(r"C:\logs\other.txt", 1, 0)
+            .file(r"C:\logs\app.1.log", 90_000, 0)
+            .build();
+        let query = Query::parse("app", MatchMode::Prefix, &[]).unwrap();
+
+        let mut out = String::new();
+        ExportReader::new(Some(index.search(&query)), ExportFormat::Paths, &[]).unwrap()
+            .read_to_string(&mut out).unwrap();
+        let mut paths: Vec<&str> = out.lines().collect();
+        paths.sort();
+        assert_eq!(paths, [r"C:\logs\app.1.log", r"C:\logs\app.log"]);
+
+        let mut out = String::new();
+        ExportReader::new(None, ExportFormat::Json, &Column::DEFAULT).unwrap()
+            .read_to_string(&mut out).unwrap();
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&out).unwrap(), serde_json::json!([]));
+    }
+}
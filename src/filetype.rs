@@ -0,0 +1,98 @@
+// File-type categories used by the `type:` query filter and the filter dropdown
+
+use serde::{Deserialize, Serialize};
+
+// A named group of extensions (lowercase, without the leading dot)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileCategory {
+    pub name: String,
+    pub extensions: Vec<String>,
+}
+
+impl FileCategory {
+    fn builtin(name: &str, extensions: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+}
+
+pub fn builtin_categories() -> Vec<FileCategory> {
+    vec![
+        FileCategory::builtin("Documents", &[
+            "txt", "md", "pdf", "doc", "docx", "odt", "rtf", "xls", "xlsx", "ods", "csv", "ppt", "pptx", "odp", "epub", "tex",
+        ]),
+        FileCategory::builtin("Images", &[
+            "jpg", "jpeg", "png", "gif", "bmp", "tif", "tiff", "webp", "svg", "ico", "heic", "raw", "psd",
+        ]),
+        FileCategory::builtin("Audio", &[
+            "mp3", "wav", "flac", "aac", "ogg", "m4a", "wma", "opus", "aiff", "mid", "midi",
+        ]),
+        FileCategory::builtin("Video", &[
+            "mp4", "mkv", "avi", "mov", "wmv", "flv", "webm", "m4v", "mpg", "mpeg", "3gp",
+        ]),
+        FileCategory::builtin("Archives", &[
+            "zip", "rar", "7z", "tar", "gz", "bz2", "xz", "zst", "tgz", "iso", "cab",
+        ]),
+        FileCategory::builtin("Executables", &[
+            "exe", "msi", "bat", "cmd", "com", "ps1", "dll", "sys", "scr", "appx", "msix",
+        ]),
+        FileCategory::builtin("Source code", &[
+            "rs", "c", "h", "cpp", "hpp", "cc", "cs", "java", "py", "js", "ts", "jsx", "tsx", "go", "rb", "php", "swift",
+            "kt", "html", "css", "json", "toml", "yaml", "yml", "xml", "sql", "sh",
+        ]),
+    ]
+}
+
+// Built-in categories followed by the user's own. A user category with the same
+// name as a built-in one replaces it.
+pub fn all_categories(user: &[FileCategory]) -> Vec<FileCategory> {
+    let mut categories: Vec<FileCategory> = builtin_categories()
+        .into_iter()
+        .filter(|b| !user.iter().any(|u| u.name.eq_ignore_ascii_case(&b.name)))
+        .collect();
+
+    for category in user {
+        categories.push(FileCategory {
+            name: category.name.clone(),
+            extensions: category.extensions.iter()
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .collect(),
+        });
+    }
+    categories
+}
+
+// Find a category by name, case-insensitively. A unique prefix is enough ("doc", "exe").
+pub fn find_category<'a>(categories: &'a [FileCategory], name: &str) -> Option<&'a FileCategory> {
+    let wanted = name.to_lowercase();
+    if let Some(exact) = categories.iter().find(|c| c.name.to_lowercase() == wanted) {
+        return Some(exact);
+    }
+
+    let mut prefixed = categories.iter().filter(|c| c.name.to_lowercase().starts_with(&wanted));
+    match (prefixed.next(), prefixed.next()) {
+        (Some(only), None) => Some(only),
+        _ => None,
+    }
+}
+
+// Extension of a file name without the dot, as written, or "" if there is none.
+// Leading dots (".gitignore") don't count as an extension separator.
+pub fn extension_of(name: &str) -> &str {
+    match name.rfind('.') {
+        Some(pos) if pos > 0 && pos + 1 < name.len() => &name[pos + 1..],
+        _ => "",
+    }
+}
+
+// Whether an extension as written is one of `extensions`, which are lowercase. Most
+// are ASCII and compare without lowercasing a copy first.
+pub fn is_one_of(ext: &str, extensions: &[String]) -> bool {
+    if ext.is_ascii() {
+        extensions.iter().any(|e| e.eq_ignore_ascii_case(ext))
+    } else {
+        extensions.contains(&ext.to_lowercase())
+    }
+}
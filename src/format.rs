@@ -0,0 +1,48 @@
+// Formatting of index values for display and output
+
+use crate::FileEntry;
+use chrono::{Local, TimeZone};
+
+// Unix seconds as a local RFC 3339 timestamp, None when the time is unknown
+pub fn iso_time(secs: i64) -> Option<String> {
+    if secs == 0 {
+        return None;
+    }
+    Local.timestamp_opt(secs, 0).single().map(|t| t.to_rfc3339())
+}
+
+// Unix seconds as local date and time for people to read, None when unknown
+pub fn display_time(secs: i64) -> Option<String> {
+    if secs == 0 {
+        return None;
+    }
+    Local.timestamp_opt(secs, 0).single().map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+// "1.5 MB" style sizes, binary units to match the size: filter
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+// The JSON shape of a result, shared by the command line and the HTTP API
+pub fn entry_json(entry: &FileEntry, path: &str) -> serde_json::Value {
+    serde_json::json!({
+        "name": entry.name,
+        "path": path,
+        "is_dir": entry.is_dir,
+        "size": entry.size,
+        "modified": iso_time(entry.modified),
+        "created": iso_time(entry.created),
+    })
+}
@@ -0,0 +1,218 @@
+// Search history: the queries that were actually used, kept in the data directory as
+// history.toml for recall with the arrow keys and for suggestions under the search box.
+// A query counts as used once it has stayed in the box for a moment, on Enter or when a
+// result is opened from it, so the keystrokes on the way there aren't recorded.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+// Suggestions favour queries used often, but a use counts half as much a week later
+const HALF_LIFE_DAYS: f64 = 7.0;
+
+// In settings.toml:
+//   [history]
+//   enabled = false   # stop recording (what was recorded stays until cleared)
+//   limit = 200       # queries kept
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistorySettings {
+    pub enabled: bool,
+    pub limit: usize,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self { enabled: true, limit: 200 }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub query: String,
+    pub uses: u32,
+    pub last_used: i64, // Unix seconds
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct History {
+    entries: Vec<HistoryEntry>, // Most recently used first
+}
+
+impl History {
+    fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("Deep Search").join("history.toml"))
+    }
+
+    // A missing file just means no history yet
+    pub fn load() -> Result<History, String> {
+        let Some(path) = Self::path().filter(|p| p.exists()) else {
+            return Ok(History::default());
+        };
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        toml::from_str(&text).map_err(|e| format!("Invalid history file {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("No data directory to save the search history in")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let text = toml::to_string_pretty(self).map_err(|e| format!("Failed to serialize history: {}", e))?;
+        std::fs::write(&path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    // Move the query to the front, dropping the least recently used beyond `limit`
+    pub fn record(&mut self, query: &str, now: i64, limit: usize) {
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+        let uses = match self.entries.iter().position(|e| e.query == query) {
+            Some(i) => self.entries.remove(i).uses + 1,
+            None => 1,
+        };
+        self.entries.insert(0, HistoryEntry { query: query.to_string(), uses, last_used: now });
+        self.entries.truncate(limit);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // The query used `back` searches ago, 0 being the latest
+    pub fn recent(&self, back: usize) -> Option<&str> {
+        self.entries.get(back).map(|e| e.query.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    // Past queries for what has been typed so far, best first. Queries starting with it
+    // come before ones that merely contain it, and within each, the often and lately used
+    // ones first. With nothing typed, the most recent queries.
+    pub fn suggestions(&self, typed: &str, now: i64, max: usize) -> Vec<&str> {
+        let typed = typed.trim().to_lowercase();
+        if typed.is_empty() {
+            return self.entries.iter().take(max).map(|e| e.query.as_str()).collect();
+        }
+
+        let mut matches: Vec<(bool, f64, &str)> = self.entries.iter()
+            .filter_map(|e| {
+                let query = e.query.to_lowercase();
+                (query != typed && query.contains(&typed)).then(|| (query.starts_with(&typed), score(e, now), e.query.as_str()))
+            })
+            .collect();
+        matches.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.total_cmp(&a.1)));
+        matches.into_iter().take(max).map(|(_, _, query)| query).collect()
+    }
+}
+
+fn score(entry: &HistoryEntry, now: i64) -> f64 {
+    let age_days = (now - entry.last_used).max(0) as f64 / 86_400.0;
+    entry.uses as f64 * 0.5f64.powf(age_days / HALF_LIFE_DAYS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86_400;
+
+    fn queries(history: &History) -> Vec<&str> {
+        (0..history.len()).filter_map(|i| history.recent(i)).collect()
+    }
+
+    #[test]
+    fn record_keeps_the_latest_first() {
+        let mut history = History::default();
+        for (i, query) in ["a", "b", "c", "a"].into_iter().enumerate() {
+            history.record(query, i as i64, 10);
+        }
+        assert_eq!(queries(&history), ["a", "c", "b"]);
+        assert_eq!(history.entries[0].uses, 2, "Used again, not recorded twice");
+        assert_eq!(history.entries[0].last_used, 3);
+    }
+
+    #[test]
+    fn record_trims_and_skips_empty_queries() {
+        let mut history = History::default();
+        history.record("  report  ", 0, 10);
+        history.record("report", 1, 10);
+        history.record("   ", 2, 10);
+        assert_eq!(queries(&history), ["report"]);
+        assert_eq!(history.entries[0].uses, 2);
+    }
+
+    #[test]
+    fn record_drops_the_least_recent_beyond_the_limit() {
+        let mut history = History::default();
+        for (i, query) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            history.record(query, i as i64, 3);
+        }
+        assert_eq!(queries(&history), ["d", "c", "b"]);
+
+        // A smaller limit takes effect on the next query
+        history.record("b", 4, 2);
+        assert_eq!(queries(&history), ["b", "d"]);
+    }
+
+    #[test]
+    fn record_tells_case_apart() {
+        let mut history = History::default();
+        history.record("Report", 0, 10);
+        history.record("report", 1, 10);
+        assert_eq!(queries(&history), ["report", "Report"]);
+    }
+
+    #[test]
+    fn suggestions_without_typing_are_the_latest() {
+        let mut history = History::default();
+        for (i, query) in ["a", "b", "c"].into_iter().enumerate() {
+            history.record(query, i as i64, 10);
+        }
+        assert_eq!(history.suggestions("  ", 10, 2), ["c", "b"]);
+    }
+
+    #[test]
+    fn suggestions_put_prefixes_before_other_matches() {
+        let mut history = History::default();
+        let now = 10 * DAY;
+        // The contained match is used far more, the prefix still comes first
+        for _ in 0..5 {
+            history.record("old report", now, 10);
+        }
+        history.record("report 2024", now, 10);
+        history.record("unrelated", now, 10);
+        assert_eq!(history.suggestions("rep", now, 10), ["report 2024", "old report"]);
+    }
+
+    #[test]
+    fn suggestions_favour_often_and_lately_used() {
+        let mut history = History::default();
+        let now = 30 * DAY;
+        history.record("ext:pdf", now - 28 * DAY, 10);
+        history.record("ext:pdf", now - 28 * DAY, 10);
+        history.record("ext:png", now - DAY, 10);
+        history.record("ext:psd", now - DAY, 10);
+        history.record("ext:psd", now - DAY, 10);
+        // Two uses four weeks ago count for less than one yesterday
+        assert_eq!(history.suggestions("ext:p", now, 10), ["ext:psd", "ext:png", "ext:pdf"]);
+        assert_eq!(history.suggestions("ext:p", now, 1), ["ext:psd"]);
+    }
+
+    #[test]
+    fn suggestions_ignore_case_and_skip_what_was_typed() {
+        let mut history = History::default();
+        history.record("Report", 0, 10);
+        history.record("Report Final", 1, 10);
+        assert_eq!(history.suggestions("REPORT", 1, 10), ["Report Final"]);
+        assert_eq!(history.suggestions("final", 1, 10), ["Report Final"]);
+    }
+}
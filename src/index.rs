@@ -0,0 +1,110 @@
+// The searchable index as shared between the GUI and the servers that query it.
+// The GUI publishes a new snapshot after every scan or rule change; readers clone
+// the snapshot (a few Arcs) and search it without holding the lock.
+
+use crate::exclude::ExclusionSet;
+use crate::filetype::FileCategory;
+use crate::query::{Filter, MatchMode, Query};
+use crate::sort::SortKey;
+use crate::{FileEntry, FolderTree, Index, Search};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+
+#[derive(Clone, Default)]
+pub struct IndexSnapshot {
+    pub data: Arc<Vec<FileEntry>>, // Sorted by (drive_idx, id), see scan_all_drives
+    pub drives: Arc<Vec<String>>,
+    pub folders: Arc<FolderTree>, // Built from data
+    pub categories: Arc<Vec<FileCategory>>,
+    pub exclusions: Option<Arc<ExclusionSet>>,
+    // False until the first scan has finished
+    pub ready: bool,
+}
+
+pub type SharedIndex = Arc<RwLock<IndexSnapshot>>;
+
+// One search with paging and ordering, as the command line and the servers ask for it.
+// Also the request format of the IPC protocol, see ipc.rs.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchRequest {
+    pub query: String,
+    pub mode: MatchMode,
+    // Include hidden/system files and excluded items
+    pub all: bool,
+    // Include only one of the two
+    pub hidden: bool,
+    pub excluded: bool,
+    pub sort: Option<SortKey>,
+    // Flips the sort key's usual direction, or the unsorted order without one
+    pub reverse: bool,
+    pub offset: usize,
+    pub count: Option<usize>,
+}
+
+impl SearchRequest {
+    // Page and order an engine search as asked
+    pub fn apply<'a>(&self, search: Search<'a>) -> Search<'a> {
+        let mut search = search.page(self.offset, self.count.unwrap_or(usize::MAX));
+        if let Some(key) = self.sort {
+            search = search.sort(key, key.descending_by_default());
+        }
+        if self.reverse {
+            search = search.reverse();
+        }
+        search
+    }
+}
+
+impl IndexSnapshot {
+    pub fn current(shared: &SharedIndex) -> IndexSnapshot {
+        shared.read().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    // The parsed and resolved query, with the same defaults as the GUI: hidden/system
+    // files and excluded items are left out unless the request asks for them. An empty
+    // query is returned as is, and callers treat it as finding nothing.
+    pub fn query(&self, request: &SearchRequest) -> Result<Query, String> {
+        let mut query = Query::parse(&request.query, request.mode, &self.categories)?;
+        if query.is_empty() {
+            return Ok(query);
+        }
+
+        query.resolve(&self.data, &self.drives, &self.folders)?;
+        if !request.all && !request.hidden {
+            query.hide_hidden();
+        }
+        if let Some(exclusions) = self.exclusions.as_ref().filter(|_| !request.all && !request.excluded) {
+            query.filters.push(Filter::Not(Box::new(Filter::Excluded(exclusions.clone()))));
+        }
+        Ok(query)
+    }
+
+    // The whole index, to search with search() below. Nothing is copied.
+    pub fn index(&self) -> Index {
+        Index::from_shared(self.data.clone(), self.drives.clone(), self.folders.clone())
+    }
+
+    // The engine search for the request, paged and ordered, to pull hits from one at a
+    // time. None for an empty query.
+    pub fn search<'a>(&self, index: &'a Index, request: &SearchRequest) -> Result<Option<Search<'a>>, String> {
+        let query = self.query(request)?;
+        if query.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(request.apply(index.search(&query))))
+    }
+
+    // The requested page of results plus the total number of matches. Only the page is
+    // copied out of the index, however many entries match; to go through every match,
+    // pull the hits from search() instead.
+    pub fn run(&self, request: &SearchRequest) -> Result<(usize, Vec<FileEntry>), String> {
+        let index = self.index();
+        let Some(search) = self.search(&index, request)? else {
+            return Ok((0, Vec::new()));
+        };
+        let total = search.total();
+        let page = search.map(|hit| hit.entry().clone()).collect();
+        Ok((total, page))
+    }
+}
@@ -0,0 +1,149 @@
+// Local IPC endpoint, so `deep_search search` and editor plugins can query the index of
+// a running instance instead of scanning the drives again. Only reachable from this
+// machine: the named pipe \\.\pipe\deep_search on Windows, a Unix socket in the user's
+// runtime directory elsewhere.
+//
+// Protocol: newline-delimited JSON both ways. The client sends one request per line,
+// of which only "query" is required:
+//   {"query": "report ext:pdf", "mode": "prefix", "all": false, "sort": "size",
+//    "reverse": false, "offset": 0, "count": 100}
+// (mode is "prefix" or "regex"; sort is name, path, size, modified or created.) The
+// server answers with one line per result, the same object `search --json` prints,
+// then {"done": true, "total": N}. A failed request gets a single {"error": "..."} line
+// instead. The connection stays open for more requests until the client closes it.
+
+use crate::format::entry_json;
+use crate::index::{IndexSnapshot, SearchRequest, SharedIndex};
+use crate::resolve_path;
+use interprocess::local_socket::prelude::*;
+use interprocess::local_socket::{ListenerOptions, Name, Stream};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How long a request waits for the first scan of a freshly started instance
+const READY_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[cfg(windows)]
+fn socket_name() -> io::Result<Name<'static>> {
+    use interprocess::local_socket::GenericNamespaced;
+    "deep_search".to_ns_name::<GenericNamespaced>()
+}
+
+// The runtime directory is private to the user, unlike the abstract socket namespace
+#[cfg(not(windows))]
+fn socket_name() -> io::Result<Name<'static>> {
+    use interprocess::local_socket::GenericFilePath;
+    let dir = dirs::runtime_dir().unwrap_or_else(std::env::temp_dir);
+    dir.join("deep_search.sock").to_fs_name::<GenericFilePath>()
+}
+
+// Start answering requests in the background
+pub fn start(index: SharedIndex) -> Result<(), String> {
+    let listener = bind()?;
+    thread::spawn(move || {
+        for stream in listener.incoming().filter_map(Result::ok) {
+            let index = index.clone();
+            // One thread per client; editors tend to keep their connection open
+            thread::spawn(move || {
+                let _ = serve(stream, &index);
+            });
+        }
+    });
+    Ok(())
+}
+
+fn bind() -> Result<LocalSocketListener, String> {
+    let failed = |e: io::Error| format!("IPC endpoint could not be created: {}", e);
+    let name = socket_name().map_err(failed)?;
+    match ListenerOptions::new().name(name.clone()).create_sync() {
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+            if Stream::connect(name.clone()).is_ok() {
+                return Err("Another Deep Search instance is already answering command-line searches".to_string());
+            }
+            // Nobody is listening: a crashed instance left its socket file behind
+            ListenerOptions::new().name(name).try_overwrite(true).create_sync().map_err(failed)
+        }
+        result => result.map_err(failed),
+    }
+}
+
+fn serve(stream: Stream, index: &SharedIndex) -> io::Result<()> {
+    let reader = BufReader::new(&stream);
+    let mut out = BufWriter::new(&stream);
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match answer(&line, index) {
+            Ok((snapshot, total, page)) => {
+                for entry in &page {
+                    let path = resolve_path(entry, &snapshot.data, &snapshot.drives);
+                    writeln!(out, "{}", entry_json(entry, &path))?;
+                }
+                writeln!(out, "{}", serde_json::json!({ "done": true, "total": total }))?;
+            }
+            Err(e) => writeln!(out, "{}", serde_json::json!({ "error": e }))?,
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+fn answer(line: &str, index: &SharedIndex) -> Result<(IndexSnapshot, usize, Vec<crate::FileEntry>), String> {
+    let request: SearchRequest = serde_json::from_str(line).map_err(|e| format!("Invalid request: {}", e))?;
+    let snapshot = ready_snapshot(index)?;
+    let (total, page) = snapshot.run(&request)?;
+    Ok((snapshot, total, page))
+}
+
+// A client that connects right after startup waits for the scan instead of getting nothing
+fn ready_snapshot(index: &SharedIndex) -> Result<IndexSnapshot, String> {
+    let started = Instant::now();
+    loop {
+        let snapshot = IndexSnapshot::current(index);
+        if snapshot.ready {
+            return Ok(snapshot);
+        }
+        if started.elapsed() > READY_TIMEOUT {
+            return Err("The index is still being built, try again shortly".to_string());
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+}
+
+// Run a search on the running instance, handing each result to `on_result`, which can
+// return false to stop early. Returns the total number of matches (when stopped early,
+// how many were handed over so far), or None when no instance is running.
+pub fn query(request: &SearchRequest, mut on_result: impl FnMut(serde_json::Value) -> bool) -> Result<Option<usize>, String> {
+    let Ok(stream) = socket_name().and_then(Stream::connect) else {
+        return Ok(None);
+    };
+    let lost = |e: io::Error| format!("Lost connection to Deep Search: {}", e);
+
+    let mut line = serde_json::to_string(request).map_err(|e| format!("Failed to encode request: {}", e))?;
+    line.push('\n');
+    (&stream).write_all(line.as_bytes()).map_err(lost)?;
+
+    let mut received = 0;
+    for line in BufReader::new(&stream).lines() {
+        let value: serde_json::Value = serde_json::from_str(&line.map_err(lost)?)
+            .map_err(|e| format!("Invalid response from Deep Search: {}", e))?;
+        if let Some(error) = value.get("error") {
+            return Err(error.as_str().unwrap_or("Unknown error").to_string());
+        }
+        if value.get("done").is_some() {
+            let total = value.get("total").and_then(serde_json::Value::as_u64).map_or(received, |t| t as usize);
+            return Ok(Some(total));
+        }
+
+        received += 1;
+        if !on_result(value) {
+            return Ok(Some(received));
+        }
+    }
+    Err("Deep Search closed the connection before finishing".to_string())
+}
@@ -0,0 +1,224 @@
+//! Deep Search's index and search engine, usable without the app.
+//!
+//! An [`Index`] holds the entries of one or more drives in memory. Queries use the same
+//! syntax as the search bar (`report ext:pdf size:>1mb parent:D:\\docs`) and
+//! [`Index::search`] returns the hits lazily, so taking the first few is cheap.
+//!
+//! ```
+//! use deep_search::filetype::builtin_categories;
+//! use deep_search::query::{MatchMode, Query};
+//! use deep_search::Index;
+//!
+//! let index = Index::builder()
+//!     .file(r"C:\projects\report.pdf", 120_000, 1_700_000_000)
+//!     .file(r"C:\projects\report-draft.docx", 80_000, 1_690_000_000)
+//!     .file(r"D:\backup\report.pdf", 119_000, 1_600_000_000)
+//!     .build();
+//!
+//! let query = Query::parse("report ext:pdf", MatchMode::Prefix, &builtin_categories()).unwrap();
+//! let paths: Vec<String> = index.search(&query).map(|hit| hit.path()).collect();
+//! assert_eq!(paths, [r"C:\projects\report.pdf", r"D:\backup\report.pdf"]);
+//! ```
+
+pub mod actions;
+pub mod attributes;
+pub mod efu;
+pub mod encoding;
+mod engine;
+pub mod exclude;
+pub mod export;
+pub mod filetype;
+pub mod format;
+pub mod history;
+pub mod index;
+pub mod query;
+pub mod sort;
+
+pub use engine::{CancelToken, Hit, Index, IndexBuilder, Search};
+
+use query::Scope;
+use std::collections::HashMap;
+
+// Represents a single file or directory entry in the MFT
+#[derive(Clone, Debug)]
+pub struct FileEntry {
+    pub id: u64,
+    pub parent_id: u64,
+    pub name: String,
+    pub is_dir: bool,
+    pub drive_idx: u8,
+    pub size: u64, // Bytes, 0 for directories
+    pub modified: i64, // Unix seconds, 0 if unknown
+    pub created: i64,
+    pub attributes: u32, // FILE_ATTRIBUTE_* bits from the USN record
+    pub order: u32, // Position in a depth-first walk of the folder tree
+    pub subtree_end: u32, // One past the order of the last descendant
+}
+
+impl FileEntry {
+    // Without the dot and as written in the name, so compare it with filetype::is_one_of.
+    // Empty for directories. Taken from the name each time rather than kept alongside it.
+    pub fn ext(&self) -> &str {
+        if self.is_dir { "" } else { filetype::extension_of(&self.name) }
+    }
+}
+
+// Number entries in depth-first order so that everything under a folder has an order in
+// (folder.order, folder.subtree_end). Folder scoping then needs no parent walks at query time.
+pub fn number_subtrees(entries: &mut [FileEntry], children: &HashMap<(u8, u64), Vec<usize>>) {
+    // Volume roots point at themselves; anything whose parent we never saw starts a tree too
+    let roots: Vec<usize> = (0..entries.len())
+        .filter(|&i| {
+            let e = &entries[i];
+            e.parent_id == e.id || find_entry(entries, e.drive_idx, e.parent_id).is_none()
+        })
+        .collect();
+
+    let mut next = 0u32;
+    let mut stack: Vec<(usize, bool)> = Vec::new();
+    for root in roots {
+        stack.push((root, false));
+        while let Some((i, finished)) = stack.pop() {
+            if finished {
+                entries[i].subtree_end = next;
+                continue;
+            }
+            entries[i].order = next;
+            next += 1;
+            stack.push((i, true));
+
+            if let Some(kids) = children.get(&(entries[i].drive_idx, entries[i].id)) {
+                stack.extend(kids.iter().filter(|&&k| k != i).map(|&k| (k, false)));
+            }
+        }
+    }
+}
+
+pub fn find_entry(data: &[FileEntry], drive_idx: u8, id: u64) -> Option<&FileEntry> {
+    data.binary_search_by(|e| e.drive_idx.cmp(&drive_idx).then(e.id.cmp(&id)))
+        .ok()
+        .map(|idx| &data[idx])
+}
+
+// The folders in each folder, as indexes into the entries they were built from, so a
+// path can be walked down from the drive root without scanning every entry per name.
+// Only valid together with those entries; build a new one whenever they change.
+#[derive(Debug, Default)]
+pub struct FolderTree {
+    children: HashMap<(u8, u64), Vec<usize>>,
+}
+
+// Stands in for the parent id of top-level folders, whatever id the volume root has
+const TOP_LEVEL: u64 = u64::MAX;
+
+impl FolderTree {
+    pub fn new(data: &[FileEntry]) -> FolderTree {
+        let mut children: HashMap<(u8, u64), Vec<usize>> = HashMap::new();
+        for (i, e) in data.iter().enumerate().filter(|(_, e)| e.is_dir && e.parent_id != e.id) {
+            // Top level: the parent is the volume root (or was never seen)
+            let top = find_entry(data, e.drive_idx, e.parent_id).is_none_or(|p| p.parent_id == p.id);
+            children.entry((e.drive_idx, if top { TOP_LEVEL } else { e.parent_id })).or_default().push(i);
+        }
+        FolderTree { children }
+    }
+
+    fn child<'a>(&self, data: &'a [FileEntry], drive_idx: u8, parent_id: u64, name: &str) -> Option<&'a FileEntry> {
+        self.children.get(&(drive_idx, parent_id))?
+            .iter()
+            .map(|&i| &data[i])
+            .find(|e| e.name.eq_ignore_ascii_case(name))
+    }
+}
+
+// Resolve a folder path like "D:\repos\app" to a search scope by walking down from the
+// drive root one name at a time. Case-insensitive, '/' works as a separator too.
+pub fn find_folder(path: &str, data: &[FileEntry], drives: &[String], folders: &FolderTree) -> Option<Scope> {
+    let normalized = path.replace('/', "\\");
+    let mut parts = normalized.split('\\').filter(|p| !p.is_empty());
+
+    let drive = parts.next()?;
+    let drive_idx = drives.iter().position(|d| d.eq_ignore_ascii_case(drive))? as u8;
+
+    let mut folder: Option<&FileEntry> = None;
+    for part in parts {
+        let parent_id = folder.map_or(TOP_LEVEL, |f| f.id);
+        folder = Some(folders.child(data, drive_idx, parent_id, part)?);
+    }
+
+    Some(match folder {
+        Some(f) => Scope::Subtree { start: f.order, end: f.subtree_end },
+        None => Scope::Drive(drive_idx),
+    })
+}
+
+pub fn resolve_path(entry: &FileEntry, data: &[FileEntry], drives: &[String]) -> String {
+    let mut parts = Vec::new();
+    let mut current_id = entry.id;
+    let drive_idx = entry.drive_idx;
+    let mut safety = 0;
+
+    loop {
+        // Binary search for (drive_idx, current_id)
+        // Since data is sorted by drive_idx then id, we can find the exact entry
+        let result = data.binary_search_by(|e| {
+            e.drive_idx.cmp(&drive_idx).then(e.id.cmp(&current_id))
+        });
+
+        if let Ok(idx) = result {
+            let e = &data[idx];
+
+            // Stop at root (parent points to self)
+            if e.parent_id == current_id {
+                break;
+            }
+
+            if e.name != "." && e.name != ".." {
+                parts.push(e.name.clone());
+            }
+            current_id = e.parent_id;
+            
+            safety += 1;
+            if safety > 200 { break; } // Cycle/Depth protection
+        } else {
+            // If we can't find the parent, we assume we've reached the root.
+            break;
+        }
+    }
+    parts.reverse();
+    let path = parts.join("\\");
+    
+    // Prepend the correct drive letter
+    if let Some(drive) = drives.get(drive_idx as usize) {
+        format!("{}\\{}", drive, path)
+    } else {
+        format!("?\\{}", path) // Fallback
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::{MatchMode, Query};
+
+    fn paths(index: &Index, text: &str) -> Vec<String> {
+        let query = Query::parse(text, MatchMode::Prefix, &[]).unwrap();
+        index.search(&query).map(|hit| hit.path()).collect()
+    }
+
+    #[test]
+    fn find_folder_walks_down_by_name() {
+        let index = Index::builder()
+            .file(r"C:\src\main.rs", 1, 0)
+            .file(r"C:\src\engine\mod.rs", 1, 0)
+            .file(r"C:\docs\src\notes.rs", 1, 0)
+            .file(r"D:\src\main.rs", 1, 0)
+            .build();
+        assert_eq!(paths(&index, r"ext:rs parent:C:\src"), [r"C:\src\main.rs", r"C:\src\engine\mod.rs"]);
+        assert_eq!(paths(&index, "ext:rs parent:c:/SRC/Engine"), [r"C:\src\engine\mod.rs"]);
+        assert_eq!(paths(&index, r"ext:rs parent:C:\docs\src"), [r"C:\docs\src\notes.rs"]);
+        assert_eq!(paths(&index, "ext:rs parent:D:"), [r"D:\src\main.rs"]);
+        // Only from the drive root down, not anywhere a folder of that name is
+        assert!(paths(&index, r"ext:rs parent:C:\engine").is_empty());
+        assert!(paths(&index, r"ext:rs parent:E:\src").is_empty());
+    }
+}
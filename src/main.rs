@@ -1,100 +1,57 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // Hide console in release
 
+mod catalog;
+mod cli;
+mod ipc;
+mod mft;
+mod preview;
+mod rpc;
+mod saved;
+mod server;
+mod settings;
+mod shell;
+mod theme;
+
+// The index and search engine live in the library (lib.rs); the window, scanning and
+// servers are the app around it
+use deep_search::{actions, attributes, efu, exclude, export, filetype, format, history, index, query, sort};
+use deep_search::{number_subtrees, resolve_path, CancelToken, FileEntry, FolderTree, Index};
+
 // NEcessary imports
 use eframe::egui;
 use rayon::prelude::*;
-use std::ffi::{c_void, OsString};
-use std::mem::size_of;
-use std::os::windows::ffi::OsStrExt;
-use std::ptr;
-use std::sync::Arc;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
 use std::thread;
-use std::time::{Duration, Instant};
-use windows::core::{PCSTR, PCWSTR};
-use windows::Win32::Foundation::{GENERIC_READ, GENERIC_WRITE, HANDLE, INVALID_HANDLE_VALUE, CloseHandle};
-use windows::Win32::Storage::FileSystem::{
-    CreateFileW, GetLogicalDrives, GetDriveTypeA, FILE_FLAGS_AND_ATTRIBUTES, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
-};
-use windows::Win32::UI::Shell::ShellExecuteW;
-use windows::Win32::UI::WindowsAndMessaging::SW_SHOW;
-
-const DRIVE_REMOVABLE: u32 = 2;
-const DRIVE_FIXED: u32 = 3;
-use windows::Win32::System::IO::DeviceIoControl;
-use windows::Win32::System::Ioctl::{FSCTL_ENUM_USN_DATA, FSCTL_QUERY_USN_JOURNAL, FSCTL_CREATE_USN_JOURNAL};
-
-struct SafeHandle(HANDLE);
-impl Drop for SafeHandle {
-    fn drop(&mut self) {
-        unsafe { let _ = CloseHandle(self.0); }
-    }
-}
-
-// --- RAW NTFS STRUCTURES --- for storing the values read from teh MFT table
-
-//Similar to the USN_JOURNAL_DATA_V0 structure in C
-#[repr(C)] // Tells rust compiler to use C-style memory layout
-#[derive(Debug, Default)] // Can be printed with {:?} and has a default constructor
-struct UsnJournalData {
-    usn_journal_id: u64,
-    first_usn: i64,
-    next_usn: i64,
-    lowest_valid_usn: i64,
-    max_usn: i64,
-    maximum_size: u64,
-    allocation_delta: u64,
-}
-
-#[repr(C)]
-#[derive(Debug, Default)]
-struct CreateUsnJournalData {
-    maximum_size: u64,
-    allocation_delta: u64,
-}
-
-// Similar to the MFT_ENUM_DATA structure in C
-#[repr(C)]
-struct MftEnumData {
-    start_file_reference_number: u64,
-    low_usn: i64,
-    high_usn: i64,
-}
-
- // Similar to the USN_RECORD structure in C
-#[repr(C)]
-#[derive(Debug, Clone, Copy)]
-struct UsnRecordHeader {
-    record_length: u32,
-    major_version: u16,
-    minor_version: u16,
-    file_reference_number: u64,
-    parent_file_reference_number: u64,
-    usn: i64,
-    timestamp: i64,
-    reason: u32,
-    source_info: u32,
-    security_id: u32,
-    file_attributes: u32,
-    file_name_length: u16,
-    file_name_offset: u16,
-}
-
-const USN_RECORD_HEADER_SIZE: usize = 60;
-
-const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x00000010; // A bitmask indicating a directory
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use catalog::Catalog;
+use attributes::{attribute_names, is_hidden_or_system, notable_letters};
+use exclude::{ExcludeRules, ExclusionSet};
+use export::{Column, ExportFormat, Exporter};
+use filetype::{all_categories, FileCategory};
+use index::{IndexSnapshot, SharedIndex};
+use query::{Filter, MatchMode, Query};
+use history::History;
+use mft::{get_drives, scan_drive, volume_info, Volume};
+use preview::Content;
+use saved::SavedSearch;
+use settings::{ScanSettings, Settings};
+use sort::SortKey;
+use theme::ThemeSettings;
+
+
+// Results are loaded this many at a time, so a one-letter search shows up at once
+const RESULT_PAGE: usize = 1000;
+
+// How long a query has to stay in the search box to count as used, see history.rs
+const HISTORY_DELAY: Duration = Duration::from_millis(1500);
+const SUGGESTIONS: usize = 8;
 
 // --- APP DATA STRUCTURES ---
  
-// Represents a single file or directory entry in the MFT
-#[derive(Clone, Debug)]
-struct FileEntry {
-    id: u64,
-    parent_id: u64,
-    name: String,
-    is_dir: bool,
-    drive_idx: u8,
-}
-
 // Application state enum to switch between different UI states
 enum AppState {
     Initializing,
@@ -103,27 +60,273 @@ enum AppState {
     Error(String),
 }
 
+// What a search thread sends back, tagged with the search's id
+enum SearchUpdate {
+    Page(Vec<FileEntry>, bool), // The next hits, and whether more may follow
+    Total(usize, Duration),     // The full count, from a second thread
+    Error(String),
+}
+
+// What scanning a single drive again sends back
+struct Rescanned {
+    base: Arc<Vec<FileEntry>>, // The index it started from
+    entries: Option<(Vec<FileEntry>, FolderTree)>, // That index with the drive's entries replaced, unless it failed
+    status: DriveStatus,
+}
+
+// What changing the file lists sends back
+struct Relisted {
+    base: Arc<Vec<FileEntry>>, // The index it started from
+    entries: Vec<FileEntry>, // That index with the lists' drives dropped or added
+    folders: FolderTree,
+    drives: Vec<String>,
+    sources: Vec<DriveSource>,
+    errors: Vec<String>, // Lists that couldn't be loaded
+}
+
 // Main application struct
 struct DeepSearchApp {
     state: AppState,
     file_data: Arc<Vec<FileEntry>>, // Read-only after scan
+    folders: Arc<FolderTree>, // Built from file_data, for parent: and folder rules
     drives: Arc<Vec<String>>,
     scan_errors: Vec<String>,
+    drive_status: Vec<DriveStatus>, // Every drive of the last scan, see the drives window
+    drive_sources: Vec<DriveSource>, // Per drive, where its entries came from
+    rescanning: Option<String>, // The drive being scanned again on its own
     search_query: String,
-    search_results: Vec<FileEntry>,
-    search_stats: Option<(usize, Duration)>,
+    search_error: Option<String>,
+    search_results: Vec<FileEntry>, // The pages loaded so far
+    search_stats: Option<(usize, Duration)>, // Full count and how long it took, once known
+    search_id: u64, // Tells updates of the current search from those of earlier ones
+    search_cancel: CancelToken,
+    search_more: Option<crossbeam_channel::Sender<()>>, // Asks for the next page while there is one
+    loading_more: bool,
+    current_query: Option<Query>, // For exports, which want every result, not just the loaded pages
+    history: History,
+    history_pos: Option<usize>, // While recalling with the arrow keys: how many searches back
+    history_draft: String, // What was typed before recalling
+    history_pending: Option<Instant>, // When the query last changed, until it is recorded
+    history_error: Option<String>,
+
+    // Keyboard navigation of the results
+    selected: Option<usize>, // The current row, as an index into search_results
+    selection: BTreeSet<usize>, // Rows picked for bulk actions, the current one included
+    selection_anchor: usize, // Where Shift extends the selection from
+    results_focused: bool, // The arrow keys move the selection instead of going to a text box
+    scroll_to_selected: bool,
+    visible_rows: std::ops::Range<usize>, // On screen last frame, for PageUp/PageDown
+    focus_search: bool, // Put the cursor in the search box on the next frame
+
+    // File type filtering
+    categories: Arc<Vec<FileCategory>>, // Built-in + user-defined
+    type_filter: Option<usize>, // Index into categories, chosen from the dropdown
+    hide_hidden: bool, // Leave out hidden/system entries unless the query asks for attrib:
+    search_folder: String, // Folder scope from the UI, same as parent: in the query
+    match_mode: MatchMode,
+    sort: Option<SortKey>, // None keeps index order
+    sort_reverse: bool, // Against the sort key's usual direction
+    settings: Settings,
+    settings_error: Option<String>,
+    applied_theme: Option<ThemeSettings>, // What egui was last given, see theme.rs
+
+    // Exclusion rules, resolved against the current index
+    exclusions: Option<Arc<ExclusionSet>>,
+    include_excluded: bool, // Temporarily show excluded items too
+    exclude_editor: Option<ExcludeEditor>,
+    export_dialog: Option<ExportDialog>,
+    file_lists_window: Option<FileListsWindow>,
+    catalogs: Vec<Catalog>, // Offline catalogs, see catalog.rs
+    catalog_error: Option<String>,
+    catalog_window: Option<CatalogWindow>,
+    saved_window: Option<SavedWindow>,
+    move_window: Option<MoveWindow>,
+    trash_confirm: Option<TrashConfirm>,
+    action_status: Option<Result<String, String>>, // What the last bulk action did
+    properties_window: Option<PropertiesWindow>,
+    settings_window: Option<SettingsWindow>,
+    drives_window: Option<DrivesWindow>,
+    preview: Option<Preview>, // Of the current row, while the preview pane is shown
+
+    // What the HTTP server searches, republished whenever the index or rules change
+    shared_index: SharedIndex,
+    server_error: Option<String>,
+    ipc_error: Option<String>,
     
     // Communication
     rx_progress: crossbeam_channel::Receiver<(u64, String)>,
     tx_progress: crossbeam_channel::Sender<(u64, String)>,
-    rx_data: crossbeam_channel::Receiver<(Vec<FileEntry>, Vec<String>, Vec<String>)>,
-    tx_data: crossbeam_channel::Sender<(Vec<FileEntry>, Vec<String>, Vec<String>)>,
+    rx_data: crossbeam_channel::Receiver<ScanResult>,
+    tx_data: crossbeam_channel::Sender<ScanResult>,
     rx_error: crossbeam_channel::Receiver<String>,
     tx_error: crossbeam_channel::Sender<String>,
     
     // Search Async
-    rx_search: crossbeam_channel::Receiver<(String, Vec<FileEntry>, Duration)>,
-    tx_search: crossbeam_channel::Sender<(String, Vec<FileEntry>, Duration)>,
+    rx_search: crossbeam_channel::Receiver<(u64, SearchUpdate)>,
+    tx_search: crossbeam_channel::Sender<(u64, SearchUpdate)>,
+    rx_exclusions: crossbeam_channel::Receiver<Result<ExclusionSet, String>>,
+    tx_exclusions: crossbeam_channel::Sender<Result<ExclusionSet, String>>,
+    rx_export: crossbeam_channel::Receiver<Result<(usize, PathBuf), String>>,
+    tx_export: crossbeam_channel::Sender<Result<(usize, PathBuf), String>>,
+    rx_index_export: crossbeam_channel::Receiver<Result<(usize, PathBuf), String>>,
+    tx_index_export: crossbeam_channel::Sender<Result<(usize, PathBuf), String>>,
+    rx_catalog: crossbeam_channel::Receiver<Result<Catalog, String>>,
+    tx_catalog: crossbeam_channel::Sender<Result<Catalog, String>>,
+    rx_rescan: crossbeam_channel::Receiver<Rescanned>,
+    tx_rescan: crossbeam_channel::Sender<Rescanned>,
+    rx_relist: crossbeam_channel::Receiver<Relisted>,
+    tx_relist: crossbeam_channel::Sender<Relisted>,
+    rx_preview: crossbeam_channel::Receiver<(String, Result<Content, String>)>,
+    preview_requests: crossbeam_channel::Sender<String>, // Paths for the preview worker, see preview.rs
+}
+
+// Edit buffers for the exclusions window, one rule per line
+struct ExcludeEditor {
+    drive: Option<String>, // None edits the rules for all drives
+    folders: String,
+    patterns: String,
+    attributes: String,
+    error: Option<String>,
+}
+
+impl ExcludeEditor {
+    fn load(settings: &Settings, drive: Option<String>) -> Self {
+        let rules = match &drive {
+            Some(d) => settings.exclude.drives.get(d).cloned().unwrap_or_default(),
+            None => settings.exclude.all.clone(),
+        };
+        Self {
+            drive,
+            folders: rules.folders.join("\n"),
+            patterns: rules.patterns.join("\n"),
+            attributes: rules.attributes,
+            error: None,
+        }
+    }
+
+    fn rules(&self) -> ExcludeRules {
+        let lines = |text: &str| -> Vec<String> {
+            text.lines().map(str::trim).filter(|l| !l.is_empty()).map(str::to_string).collect()
+        };
+        ExcludeRules {
+            folders: lines(&self.folders),
+            patterns: lines(&self.patterns),
+            attributes: self.attributes.trim().to_string(),
+        }
+    }
+}
+
+// Choices in the export window
+struct ExportDialog {
+    format: ExportFormat,
+    columns: Vec<Column>,
+    path: String,
+    running: bool,
+    status: Option<Result<String, String>>,
+    selection: Option<Vec<FileEntry>>, // Only these rather than every result
+}
+
+impl ExportDialog {
+    fn new() -> Self {
+        let format = ExportFormat::Csv;
+        let path = dirs::document_dir()
+            .unwrap_or_default()
+            .join(format!("Deep Search results.{}", format.extension()));
+        Self {
+            format,
+            columns: Column::DEFAULT.to_vec(),
+            path: path.display().to_string(),
+            running: false,
+            status: None,
+            selection: None,
+        }
+    }
+}
+
+// The file lists window: lists loaded as virtual drives, and exporting this machine's index
+struct FileListsWindow {
+    new_list: String,
+    export_path: String,
+    exporting: bool,
+    status: Option<Result<String, String>>,
+}
+
+impl FileListsWindow {
+    fn new() -> Self {
+        let export_path = dirs::document_dir().unwrap_or_default().join("index.efu");
+        Self {
+            new_list: String::new(),
+            export_path: export_path.display().to_string(),
+            exporting: false,
+            status: None,
+        }
+    }
+}
+
+// The offline catalogs window
+struct CatalogWindow {
+    drive: Option<String>, // Drive to save a catalog of
+    name: String,
+    saving: bool,
+    status: Option<Result<String, String>>,
+}
+
+// The saved searches window
+struct SavedWindow {
+    name: String, // To save the current search under
+    status: Option<Result<String, String>>,
+}
+
+// Moving the selection: the folder to move it to
+struct MoveWindow {
+    folder: String,
+}
+
+// Asking before the selection goes to the Recycle Bin
+struct TrashConfirm {
+    paths: Vec<String>,
+    elsewhere: usize, // Selected but not on this computer, so left out
+}
+
+// What the context menu of a result row asks for
+#[derive(Clone, Copy)]
+enum RowAction {
+    Open,
+    OpenWith,
+    ShowInFolder,
+    CopyPath,
+    CopyName,
+    CopyParent,
+    Terminal,
+    Properties,
+}
+
+// The properties window of one result
+struct PropertiesWindow {
+    entry: FileEntry,
+    path: String,
+    source: String, // Where the entry is known from
+    local: bool, // On this computer, so it can be opened
+}
+
+// The drives window: which drives to index, until saved
+struct DrivesWindow {
+    skip: Vec<String>,
+}
+
+// The settings window edits a copy, which replaces the real settings only once saved
+struct SettingsWindow {
+    draft: Settings,
+    status: Option<Result<String, String>>,
+}
+
+// The preview pane's entry, and its contents once loaded
+struct Preview {
+    entry: FileEntry,
+    path: String,
+    local: bool,
+    content: Option<Result<Content, String>>, // None while loading, or when there is nothing to load
+    texture: Option<egui::TextureHandle>, // The thumbnail of an image, once on the GPU
 }
 
 // --- APP LOGIC IMPLEMENTATION ---
@@ -133,15 +336,97 @@ impl DeepSearchApp {
         let (tx_data, rx_data) = crossbeam_channel::bounded(1);
         let (tx_error, rx_error) = crossbeam_channel::bounded(1);
         let (tx_search, rx_search) = crossbeam_channel::unbounded();
+        let (tx_exclusions, rx_exclusions) = crossbeam_channel::unbounded();
+        let (tx_export, rx_export) = crossbeam_channel::unbounded();
+        let (tx_index_export, rx_index_export) = crossbeam_channel::unbounded();
+        let (tx_catalog, rx_catalog) = crossbeam_channel::unbounded();
+        let (tx_rescan, rx_rescan) = crossbeam_channel::unbounded();
+        let (tx_relist, rx_relist) = crossbeam_channel::unbounded();
+        let (tx_preview, rx_preview) = crossbeam_channel::unbounded();
+
+        let (settings, settings_error) = match Settings::load_repaired() {
+            Ok((settings, problems)) => (settings, (!problems.is_empty()).then(|| problems.join("\n"))),
+            Err(e) => (Settings::default(), Some(e)),
+        };
+        let mut catalog_error = None;
+        let catalogs = catalog::load_all().unwrap_or_else(|e| {
+            catalog_error = Some(e);
+            Vec::new()
+        });
+
+        let mut history_error = None;
+        let history = History::load().unwrap_or_else(|e| {
+            history_error = Some(e);
+            History::default()
+        });
+
+        let shared_index: SharedIndex = Arc::new(RwLock::new(IndexSnapshot::default()));
+        let server_error = if settings.server.enabled {
+            server::start(&settings.server, shared_index.clone()).err()
+        } else {
+            None
+        };
+        let ipc_error = ipc::start(shared_index.clone()).err();
 
         Self {
             state: AppState::Initializing,
             file_data: Arc::new(Vec::new()),
+            folders: Arc::new(FolderTree::default()),
             drives: Arc::new(Vec::new()),
             scan_errors: Vec::new(),
+            drive_status: Vec::new(),
+            drive_sources: Vec::new(),
+            rescanning: None,
             search_query: String::new(),
+            search_error: None,
             search_results: Vec::new(),
             search_stats: None,
+            search_id: 0,
+            search_cancel: CancelToken::new(),
+            search_more: None,
+            loading_more: false,
+            current_query: None,
+            history,
+            history_pos: None,
+            history_draft: String::new(),
+            history_pending: None,
+            history_error,
+            selected: None,
+            selection: BTreeSet::new(),
+            selection_anchor: 0,
+            results_focused: false,
+            scroll_to_selected: false,
+            visible_rows: 0..0,
+            focus_search: false,
+            categories: Arc::new(all_categories(&settings.categories)),
+            type_filter: None,
+            hide_hidden: true,
+            search_folder: String::new(),
+            match_mode: MatchMode::Prefix,
+            sort: None,
+            sort_reverse: false,
+            settings,
+            settings_error,
+            applied_theme: None,
+            exclusions: None,
+            include_excluded: false,
+            exclude_editor: None,
+            export_dialog: None,
+            file_lists_window: None,
+            catalogs,
+            catalog_error,
+            catalog_window: None,
+            saved_window: None,
+            move_window: None,
+            trash_confirm: None,
+            action_status: None,
+            properties_window: None,
+            settings_window: None,
+            drives_window: None,
+            preview: None,
+            shared_index,
+            server_error,
+            ipc_error,
             rx_progress,
             tx_progress,
             rx_data,
@@ -150,6 +435,20 @@ impl DeepSearchApp {
             tx_error,
             rx_search,
             tx_search,
+            rx_exclusions,
+            tx_exclusions,
+            rx_export,
+            tx_export,
+            rx_index_export,
+            tx_index_export,
+            rx_catalog,
+            tx_catalog,
+            rx_rescan,
+            tx_rescan,
+            rx_relist,
+            tx_relist,
+            rx_preview,
+            preview_requests: preview::start(tx_preview),
         }
     }
     // Start scanning drives in a separate thread to prevent UI blocking 
@@ -164,11 +463,13 @@ impl DeepSearchApp {
         let tx_progress = self.tx_progress.clone();
         let tx_data = self.tx_data.clone();
         let tx_error = self.tx_error.clone();
+        let file_lists = self.settings.file_lists.clone();
+        let scan = self.settings.scan.clone();
 
         thread::spawn(move || {
-            match scan_all_drives(tx_progress) {
-                Ok((data, drives, errors)) => {
-                    let _ = tx_data.send((data, drives, errors));
+            match scan_all_drives(tx_progress, &file_lists, &scan) {
+                Ok(result) => {
+                    let _ = tx_data.send(result);
                 }
                 Err(e) => {
                     let _ = tx_error.send(e);
@@ -177,43 +478,1361 @@ impl DeepSearchApp {
         });
     }
 
+    // Scan one drive again while the rest of the index stays in use
+    fn start_rescan(&mut self, drive: &str) {
+        let Some(drive_idx) = self.drives.iter().position(|d| d == drive) else { return };
+        self.rescanning = Some(drive.to_string());
+
+        let data = self.file_data.clone();
+        let drives = self.drives.clone();
+        let scan = self.settings.scan.clone();
+        let tx = self.tx_rescan.clone();
+
+        thread::spawn(move || {
+            let (entries, status) = rescan_drive(&data, &drives, drive_idx as u8, &scan);
+            let entries = entries.map(|entries| {
+                let folders = FolderTree::new(&entries);
+                (entries, folders)
+            });
+            let _ = tx.send(Rescanned { base: data, entries, status });
+        });
+    }
+
+    fn show_drives_window(&mut self, ctx: &egui::Context) {
+        let Some(window) = &mut self.drives_window else { return };
+        let mut open = true;
+        let mut rescan = None;
+        let mut apply = false;
+        let busy = self.rescanning.is_some() || !matches!(self.state, AppState::Ready);
+
+        egui::Window::new("Drives").open(&mut open).collapsible(false).show(ctx, |ui| {
+            if self.drive_status.is_empty() {
+                ui.label("No fixed or removable drives found.");
+            }
+            egui::Grid::new("drives_grid").num_columns(8).striped(true).spacing([12.0, 4.0]).show(ui, |ui| {
+                for heading in ["Index", "Drive", "Label", "File system", "Entries", "Last scan", "Status", ""] {
+                    ui.label(egui::RichText::new(heading).strong());
+                }
+                ui.end_row();
+
+                for status in &self.drive_status {
+                    let skipped = window.skip.iter().any(|d| d.trim_end_matches('\\').eq_ignore_ascii_case(&status.drive));
+                    let mut included = !skipped;
+                    if ui.checkbox(&mut included, "").on_hover_text("Include this drive in the index").changed() {
+                        if included {
+                            window.skip.retain(|d| !d.trim_end_matches('\\').eq_ignore_ascii_case(&status.drive));
+                        } else {
+                            window.skip.push(status.drive.clone());
+                        }
+                    }
+                    ui.label(&status.drive);
+                    ui.label(&status.label);
+                    ui.label(&status.filesystem);
+                    ui.label(if matches!(status.state, DriveState::Indexed) { status.entries.to_string() } else { "–".to_string() });
+                    ui.label(status.scanned_at.and_then(format::display_time).unwrap_or_else(|| "never".to_string()));
+                    match &status.state {
+                        DriveState::Indexed => { ui.label(egui::RichText::new("Indexed").color(theme::success_color(ui.visuals()))); }
+                        DriveState::Skipped => { ui.label(egui::RichText::new("Skipped").color(ui.visuals().weak_text_color())); }
+                        DriveState::Failed(e) => { ui.label(egui::RichText::new("Failed").color(ui.visuals().error_fg_color)).on_hover_text(e); }
+                    }
+                    if self.rescanning.as_ref() == Some(&status.drive) {
+                        ui.spinner();
+                    } else if ui.add_enabled(!busy && self.drives.contains(&status.drive), egui::Button::new("Rescan")).clicked() {
+                        rescan = Some(status.drive.clone());
+                    }
+                    ui.end_row();
+                }
+            });
+
+            ui.add_space(5.0);
+            ui.label(egui::RichText::new("Unticked drives are left out of the index, and their offline catalogs too.")
+                .small().color(ui.visuals().weak_text_color()));
+            let mut pending = window.skip.clone();
+            let mut saved = self.settings.scan.skip_drives.clone();
+            pending.sort();
+            saved.sort();
+            ui.horizontal(|ui| {
+                apply = ui.add_enabled(!busy && pending != saved, egui::Button::new("Save and rescan")).clicked();
+                if ui.add_enabled(!busy, egui::Button::new("Rescan all")).clicked() {
+                    apply = true;
+                }
+            });
+        });
+
+        if apply {
+            self.settings.scan.skip_drives = window.skip.clone();
+            self.settings_error = self.settings.save().err();
+            self.start_scan();
+        }
+        if let Some(drive) = rescan {
+            self.start_rescan(&drive);
+        }
+        if !open {
+            self.drives_window = None;
+        }
+    }
+
+    fn publish_index(&self) {
+        if let Ok(mut shared) = self.shared_index.write() {
+            *shared = IndexSnapshot {
+                data: self.file_data.clone(),
+                drives: self.drives.clone(),
+                folders: self.folders.clone(),
+                categories: self.categories.clone(),
+                exclusions: self.exclusions.clone(),
+                ready: true,
+            };
+        }
+    }
+
+    // Work out what the exclusion rules cover in the current index, off the UI thread
+    fn rebuild_exclusions(&mut self) {
+        let rules = self.settings.exclude.clone();
+        let data = self.file_data.clone();
+        let drives = self.drives.clone();
+        let folders = self.folders.clone();
+        let tx = self.tx_exclusions.clone();
+
+        thread::spawn(move || {
+            let _ = tx.send(ExclusionSet::build(&rules, &data, &drives, &folders));
+        });
+    }
+
+    fn save_exclude_editor(&mut self) {
+        let Some(editor) = &mut self.exclude_editor else { return };
+        if let Err(e) = attributes::parse_attributes(&editor.attributes) {
+            editor.error = Some(e);
+            return;
+        }
+
+        let rules = editor.rules();
+        match &editor.drive {
+            Some(d) if rules == ExcludeRules::default() => { self.settings.exclude.drives.remove(d); }
+            Some(d) => { self.settings.exclude.drives.insert(d.clone(), rules); }
+            None => self.settings.exclude.all = rules,
+        }
+
+        match self.settings.save() {
+            Ok(()) => editor.error = None,
+            Err(e) => editor.error = Some(e),
+        }
+        self.rebuild_exclusions();
+    }
+
+    // Write the current results to the dialog's file, off the UI thread. The search runs
+    // again in full, since only the pages scrolled to so far are loaded.
+    fn start_export(&mut self) {
+        let Some(dialog) = &mut self.export_dialog else { return };
+        let path = PathBuf::from(dialog.path.trim());
+        if path.as_os_str().is_empty() {
+            dialog.status = Some(Err("Choose a file to export to".to_string()));
+            return;
+        }
+        let query = self.current_query.clone();
+        if query.is_none() && dialog.selection.is_none() {
+            dialog.status = Some(Err("There are no results to export".to_string()));
+            return;
+        }
+        dialog.running = true;
+        dialog.status = None;
+
+        let index = Index::from_shared(self.file_data.clone(), self.drives.clone(), self.folders.clone());
+        let (format, columns) = (dialog.format, dialog.columns.clone());
+        let selection = dialog.selection.clone();
+        let sort = self.sort_order();
+        let tx = self.tx_export.clone();
+
+        thread::spawn(move || {
+            let result = std::fs::File::create(&path)
+                .and_then(|file| {
+                    let out = std::io::BufWriter::new(file);
+                    if let Some(entries) = selection {
+                        return export::export_entries(out, &entries, format, &columns, index.entries(), index.drives());
+                    }
+                    // Without a selection there is a query, see above
+                    let Some(query) = &query else { return Ok(0) };
+                    let mut exporter = Exporter::new(out, format, &columns)?;
+                    let mut search = index.search(query);
+                    if let Some((key, descending)) = sort {
+                        search = search.sort(key, descending);
+                    }
+                    for hit in search {
+                        exporter.write(hit.entry(), &hit.path())?;
+                    }
+                    exporter.finish().map(|(_, rows)| rows)
+                })
+                .map(|rows| (rows, path.clone()))
+                .map_err(|e| format!("Failed to export to {}: {}", path.display(), e));
+            let _ = tx.send(result);
+        });
+    }
+
+    // Only the lists added or removed are loaded or dropped, off the UI thread; the rest
+    // of the index stays as it is
+    fn set_file_lists(&mut self, lists: Vec<String>) {
+        self.settings.file_lists = lists.clone();
+        if let (Err(e), Some(window)) = (self.settings.save(), &mut self.file_lists_window) {
+            window.status = Some(Err(e));
+        }
+        // A scan under way started from the old lists
+        if !matches!(self.state, AppState::Ready) {
+            self.start_scan();
+            return;
+        }
+
+        let data = self.file_data.clone();
+        let drives = self.drives.clone();
+        let sources = self.drive_sources.clone();
+        let tx = self.tx_relist.clone();
+        thread::spawn(move || {
+            let _ = tx.send(relist(data, &drives, &sources, &lists));
+        });
+    }
+
+    // The scan refreshes catalogs of mounted drives, so reread them after each one
+    fn reload_catalogs(&mut self) {
+        match catalog::load_all() {
+            Ok(catalogs) => {
+                self.catalogs = catalogs;
+                self.catalog_error = None;
+            }
+            Err(e) => self.catalog_error = Some(e),
+        }
+    }
+
+    // The catalog standing in for a drive that isn't connected, if that's what this is
+    fn offline_catalog(&self, drive_idx: u8) -> Option<&Catalog> {
+        match self.drive_sources.get(drive_idx as usize)? {
+            DriveSource::Catalog(serial) => self.catalogs.iter().find(|c| c.serial == *serial),
+            _ => None,
+        }
+    }
+
+    // Save a catalog of the window's drive from the current index, off the UI thread
+    fn start_catalog_save(&mut self) {
+        let Some(window) = &mut self.catalog_window else { return };
+        let Some(drive) = window.drive.clone() else { return };
+        let Some(drive_idx) = self.drives.iter().position(|d| *d == drive) else { return };
+        window.saving = true;
+        window.status = None;
+
+        let name = window.name.trim().to_string();
+        let data = self.file_data.clone();
+        let drives = self.drives.clone();
+        let tx = self.tx_catalog.clone();
+
+        thread::spawn(move || {
+            let result = volume_info(&drive)
+                .ok_or_else(|| format!("Can't read the volume serial number of {}", drive))
+                .and_then(|volume| {
+                    let name = if !name.is_empty() {
+                        name
+                    } else if !volume.label.is_empty() {
+                        volume.label.clone()
+                    } else {
+                        format!("Drive {}", drive.trim_end_matches(':'))
+                    };
+                    catalog::save(&name, volume.serial, &volume.label, drive_idx as u8, &data, &drives)
+                });
+            let _ = tx.send(result);
+        });
+    }
+
+    fn delete_catalog(&mut self, catalog: &Catalog) {
+        if let Err(e) = catalog.delete() {
+            if let Some(window) = &mut self.catalog_window {
+                window.status = Some(Err(e));
+            }
+            return;
+        }
+        self.reload_catalogs();
+        // Its entries are in the index while the drive is away
+        if self.drive_sources.contains(&DriveSource::Catalog(catalog.serial)) {
+            self.start_scan();
+        }
+    }
+
+    // Write the live drives to an EFU file, off the UI thread
+    fn start_index_export(&mut self) {
+        let Some(window) = &mut self.file_lists_window else { return };
+        let path = PathBuf::from(window.export_path.trim());
+        window.exporting = true;
+        window.status = None;
+
+        let data = self.file_data.clone();
+        let drives = self.drives.clone();
+        let tx = self.tx_index_export.clone();
+
+        thread::spawn(move || {
+            let result = std::fs::File::create(&path)
+                .and_then(|file| efu::export_index(std::io::BufWriter::new(file), &data, &drives))
+                .map(|rows| (rows, path.clone()))
+                .map_err(|e| format!("Failed to export to {}: {}", path.display(), e));
+            let _ = tx.send(result);
+        });
+    }
+
     // Perform search asynchronously in a separate thread to prevent UI blocking based on current search_query
+    // Only the first page is produced straight away; more follow as the list is scrolled
+    // (see load_more), while a second thread counts every match for the stats line.
     fn perform_search(&mut self) {
-        let query = self.search_query.clone();
+        // Whatever the previous search was still doing is no longer wanted
+        self.search_cancel.cancel();
+        self.search_cancel = CancelToken::new();
+        self.search_id += 1;
+        self.search_more = None;
+        self.loading_more = false;
+        self.action_status = None;
+
+        let query_text = self.search_query.clone();
+        let mut query = match Query::parse(&query_text, self.match_mode, &self.categories) {
+            Ok(query) => query,
+            Err(e) => {
+                self.search_error = Some(e);
+                return;
+            }
+        };
+        self.search_error = None;
+
+        // The dropdown filter combines with whatever the query text asks for
+        if let Some(category) = self.type_filter.and_then(|i| self.categories.get(i)) {
+            query.filters.push(Filter::Extension(category.extensions.clone()));
+        }
+
+        // So does the folder box, which is a search of its own: everything in the folder
+        let folder = self.search_folder.trim();
+        if !folder.is_empty() {
+            query.add_folder(folder);
+        }
+
         if query.is_empty() {
-            self.search_results.clear();
+            self.search_results = Vec::new();
+            self.clear_selection();
             self.search_stats = None;
+            self.current_query = None;
             return;
         }
 
-        let data = self.file_data.clone();
+        if self.hide_hidden {
+            query.hide_hidden();
+        }
+        if let Some(exclusions) = self.exclusions.as_ref().filter(|_| !self.include_excluded) {
+            query.filters.push(Filter::Not(Box::new(Filter::Excluded(exclusions.clone()))));
+        }
+
+        self.current_query = Some(query.clone());
+        self.search_stats = None;
+        let index = Index::from_shared(self.file_data.clone(), self.drives.clone(), self.folders.clone());
+        let (id, cancel, sort) = (self.search_id, self.search_cancel.clone(), self.sort_order());
         let tx = self.tx_search.clone();
+        let (tx_more, rx_more) = crossbeam_channel::unbounded::<()>();
+        self.search_more = Some(tx_more);
 
         // Spawn a thread to avoid blocking the UI
         thread::spawn(move || {
             let start = Instant::now();
-            let q_lower = query.to_lowercase();
-            
-            let results: Vec<FileEntry> = data.par_iter()
-                .filter(|entry| entry.name.to_lowercase().starts_with(&q_lower))
-                .cloned()
-                .collect();
-            
-            let _ = tx.send((query, results, start.elapsed()));
+
+            if let Err(e) = index.resolve(&mut query) {
+                let _ = tx.send((id, SearchUpdate::Error(e)));
+                return;
+            }
+
+            let counter = (index.clone(), query.clone(), cancel.clone(), tx.clone());
+            thread::spawn(move || {
+                let (index, query, cancel, tx) = counter;
+                let total = index.search(&query).cancel_with(&cancel).total();
+                if !cancel.is_cancelled() {
+                    let _ = tx.send((id, SearchUpdate::Total(total, start.elapsed())));
+                }
+            });
+
+            let mut search = index.search(&query).cancel_with(&cancel);
+            if let Some((key, descending)) = sort {
+                search = search.sort(key, descending);
+            }
+            let mut hits = search.map(|hit| hit.entry().clone());
+            loop {
+                let page: Vec<FileEntry> = hits.by_ref().take(RESULT_PAGE).collect();
+                let more = page.len() == RESULT_PAGE;
+                if tx.send((id, SearchUpdate::Page(page, more))).is_err() || !more {
+                    break;
+                }
+                // Wait until the list is scrolled near the end. The next search drops the
+                // sender, which ends this one.
+                if rx_more.recv().is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    // Ask the running search for its next page, once per page
+    fn load_more(&mut self) {
+        if self.loading_more {
+            return;
+        }
+        if let Some(more) = &self.search_more {
+            self.loading_more = more.send(()).is_ok();
+        }
+    }
+
+    // Remember the query on screen as used, if it found anything
+    fn record_history(&mut self) {
+        self.history_pending = None;
+        self.history_pos = None;
+        if !self.settings.history.enabled || self.search_error.is_some() || self.search_results.is_empty() {
+            return;
+        }
+        self.history.record(&self.search_query, chrono::Utc::now().timestamp(), self.settings.history.limit);
+        self.history_error = self.history.save().err();
+    }
+
+    // Up goes further back, down comes forward again and finally back to what was typed
+    fn recall_history(&mut self, older: bool) {
+        let pos = match (self.history_pos, older) {
+            (None, true) if !self.history.is_empty() => {
+                self.history_draft = self.search_query.clone();
+                Some(0)
+            }
+            (None, _) => return,
+            (Some(pos), true) => Some((pos + 1).min(self.history.len() - 1)),
+            (Some(0), false) => None,
+            (Some(pos), false) => Some(pos - 1),
+        };
+        self.history_pos = pos;
+        self.search_query = match pos {
+            Some(pos) => self.history.recent(pos).unwrap_or_default().to_string(),
+            None => self.history_draft.clone(),
+        };
+        // Recalled queries are recorded once used, not while browsing past them
+        self.history_pending = None;
+        self.perform_search();
+    }
+
+    fn clear_history(&mut self) {
+        self.history.clear();
+        self.history_pos = None;
+        self.history_error = self.history.save().err();
+    }
+
+    // Esc: start over with an empty search box
+    fn clear_query(&mut self) {
+        self.search_query.clear();
+        self.history_pos = None;
+        self.history_pending = None;
+        self.perform_search();
+    }
+
+    fn clear_selection(&mut self) {
+        self.selected = None;
+        self.selection.clear();
+        self.selection_anchor = 0;
+    }
+
+    // Make `row` the current one. Shift selects everything from the anchor to it, Ctrl
+    // picks it out or puts it back (with Shift, adds the range), and plain selects it alone.
+    fn select_row(&mut self, row: usize, modifiers: egui::Modifiers) {
+        if modifiers.shift {
+            if !modifiers.command {
+                self.selection.clear();
+            }
+            let anchor = self.selection_anchor.min(self.search_results.len().saturating_sub(1));
+            self.selection.extend(anchor.min(row)..=anchor.max(row));
+        } else {
+            if !modifiers.command {
+                self.selection.clear();
+            }
+            if !self.selection.remove(&row) {
+                self.selection.insert(row);
+            }
+            self.selection_anchor = row;
+        }
+        self.selected = Some(row);
+    }
+
+    // Move the current row by `delta`, stopping at the ends of what is loaded. With Shift
+    // the selection stretches along.
+    fn step_selection(&mut self, delta: isize, extend: bool) {
+        let Some(last) = self.search_results.len().checked_sub(1) else { return };
+        let row = match self.selected {
+            Some(row) => row.saturating_add_signed(delta).min(last),
+            None => 0,
+        };
+        self.select_row(row, if extend { egui::Modifiers::SHIFT } else { egui::Modifiers::NONE });
+        self.scroll_to_selected = true;
+        // Keep pages coming when walking down the list, as scrolling does
+        if row + RESULT_PAGE / 2 >= self.search_results.len() {
+            self.load_more();
+        }
+    }
+
+    fn select_all(&mut self) {
+        self.selection = (0..self.search_results.len()).collect();
+        if self.selected.is_none() && !self.search_results.is_empty() {
+            self.selected = Some(0);
+        }
+    }
+
+    // Entries from file lists and offline catalogs aren't on this computer to act on
+    fn is_local(&self, entry: &FileEntry) -> bool {
+        let is_virtual = self.drives.get(entry.drive_idx as usize).is_some_and(|d| efu::is_virtual_drive(d));
+        !is_virtual && self.offline_catalog(entry.drive_idx).is_none()
+    }
+
+    // The selected rows in list order
+    fn selected_entries(&self) -> impl Iterator<Item = &FileEntry> {
+        self.selection.iter().filter_map(|&i| self.search_results.get(i))
+    }
+
+    // Paths of the selected entries that are on this computer, and how many aren't
+    fn selected_local_paths(&self) -> (Vec<String>, usize) {
+        let (local, elsewhere): (Vec<&FileEntry>, Vec<&FileEntry>) = self.selected_entries().partition(|e| self.is_local(e));
+        let paths = local.into_iter().map(|e| resolve_path(e, &self.file_data, &self.drives)).collect();
+        (paths, elsewhere.len())
+    }
+
+    // Enter opens the current result, Ctrl+Enter shows it in its folder
+    fn open_selected(&mut self, in_folder: bool) {
+        let Some(entry) = self.selected.and_then(|i| self.search_results.get(i)) else { return };
+        if !self.is_local(entry) {
+            return;
+        }
+        let path = resolve_path(entry, &self.file_data, &self.drives);
+        let result = if in_folder { shell::show_in_folder(&path) } else { shell::open(&path) };
+        match result {
+            Ok(()) => self.record_history(),
+            Err(e) => self.action_status = Some(Err(e)),
+        }
+    }
+
+    // A choice from a row's context menu
+    fn row_action(&mut self, ctx: &egui::Context, row: usize, action: RowAction) {
+        let Some(entry) = self.search_results.get(row).cloned() else { return };
+        let path = resolve_path(&entry, &self.file_data, &self.drives);
+        let result = match action {
+            RowAction::Open => shell::open(&path),
+            RowAction::OpenWith => shell::open_with(&path),
+            RowAction::ShowInFolder => shell::show_in_folder(&path),
+            RowAction::Terminal => shell::open_terminal(if entry.is_dir { &path } else { actions::parent_of(&path) }),
+            RowAction::CopyPath | RowAction::CopyName | RowAction::CopyParent => {
+                ctx.copy_text(match action {
+                    RowAction::CopyName => entry.name.clone(),
+                    RowAction::CopyParent => actions::parent_of(&path).to_string(),
+                    _ => path.clone(),
+                });
+                return;
+            }
+            RowAction::Properties => {
+                let source = match (self.offline_catalog(entry.drive_idx), self.drives.get(entry.drive_idx as usize)) {
+                    (Some(catalog), _) => format!("Offline catalog '{}' ({}), not connected", catalog.name, catalog.serial_text()),
+                    (None, Some(drive)) if efu::is_virtual_drive(drive) => format!("File list {}", drive),
+                    _ => "This computer".to_string(),
+                };
+                let local = self.is_local(&entry);
+                self.properties_window = Some(PropertiesWindow { entry, path, source, local });
+                return;
+            }
+        };
+        match result {
+            Ok(()) => self.record_history(),
+            Err(e) => self.action_status = Some(Err(e)),
+        }
+    }
+
+    // Full paths, or names, of the selection onto the clipboard
+    fn copy_selection(&mut self, ctx: &egui::Context, names: bool) {
+        let items: Vec<String> = self.selected_entries()
+            .map(|e| if names { e.name.clone() } else { resolve_path(e, &self.file_data, &self.drives) })
+            .collect();
+        if items.is_empty() {
+            return;
+        }
+        ctx.copy_text(actions::lines(&items));
+        let what = if names { "name" } else { "path" };
+        self.action_status = Some(Ok(format!("Copied {} {}{}", items.len(), what, if items.len() == 1 { "" } else { "s" })));
+    }
+
+    fn export_selection(&mut self) {
+        let mut dialog = ExportDialog::new();
+        dialog.selection = Some(self.selected_entries().cloned().collect());
+        self.export_dialog = Some(dialog);
+    }
+
+    fn move_selected(&mut self, folder: &str) {
+        let (paths, skipped) = self.selected_local_paths();
+        let folder = PathBuf::from(folder.trim());
+        let report = actions::move_into(&paths, &folder);
+        self.forget_results(&report.moved);
+
+        let mut message = format!("Moved {} of {} items to {}", report.moved.len(), paths.len(), folder.display());
+        if skipped > 0 {
+            message += &format!(" ({} not on this computer left out)", skipped);
+        }
+        // They show up in their new place with the next scan
+        self.action_status = Some(if report.failed.is_empty() {
+            Ok(message)
+        } else {
+            Err(format!("{}. {}", message, report.failed.join("; ")))
+        });
+    }
+
+    fn trash_selected(&mut self, paths: &[String]) {
+        self.action_status = Some(match shell::move_to_trash(paths) {
+            Ok(()) => {
+                self.forget_results(paths);
+                Ok(format!("Moved {} item{} to the Recycle Bin", paths.len(), if paths.len() == 1 { "" } else { "s" }))
+            }
+            Err(e) => Err(e),
         });
     }
+
+    // Take moved and deleted entries off the list. The index still has them until the
+    // next scan.
+    fn forget_results(&mut self, paths: &[String]) {
+        if paths.is_empty() {
+            return;
+        }
+        let gone: std::collections::HashSet<&str> = paths.iter().map(String::as_str).collect();
+        let before = self.search_results.len();
+        let (data, drives) = (self.file_data.clone(), self.drives.clone());
+        self.search_results.retain(|e| !gone.contains(resolve_path(e, &data, &drives).as_str()));
+        let removed = before - self.search_results.len();
+        if let Some((count, _)) = &mut self.search_stats {
+            *count = count.saturating_sub(removed);
+        }
+        self.clear_selection();
+    }
+
+    // The sort key and whether it runs descending
+    fn sort_order(&self) -> Option<(SortKey, bool)> {
+        self.sort.map(|key| (key, key.descending_by_default() != self.sort_reverse))
+    }
+
+    // Everything about the search on screen, to save under a name
+    fn current_search(&self, name: &str) -> SavedSearch {
+        SavedSearch {
+            name: name.to_string(),
+            query: self.search_query.clone(),
+            mode: self.match_mode,
+            file_type: self.type_filter.and_then(|i| self.categories.get(i)).map(|c| c.name.clone()),
+            folder: self.search_folder.trim().to_string(),
+            hide_hidden: self.hide_hidden,
+            include_excluded: self.include_excluded,
+            sort: self.sort,
+            reverse: self.sort_reverse,
+            pinned: false,
+        }
+    }
+
+    fn is_current(&self, saved: &SavedSearch) -> bool {
+        self.current_search(&saved.name) == SavedSearch { pinned: false, ..saved.clone() }
+    }
+
+    fn apply_saved(&mut self, saved: &SavedSearch) {
+        self.search_query = saved.query.clone();
+        self.match_mode = saved.mode;
+        // A category that has since been removed falls back to all types
+        self.type_filter = saved.file_type.as_ref()
+            .and_then(|name| self.categories.iter().position(|c| c.name.eq_ignore_ascii_case(name)));
+        self.search_folder = saved.folder.clone();
+        self.hide_hidden = saved.hide_hidden;
+        self.include_excluded = saved.include_excluded;
+        self.sort = saved.sort;
+        self.sort_reverse = saved.reverse;
+        self.perform_search();
+    }
+
+    // Change the saved searches and write them to settings.toml
+    fn update_saved_searches(&mut self, change: impl FnOnce(&mut Vec<SavedSearch>), done: String) {
+        change(&mut self.settings.saved_searches);
+        let status = self.settings.save().map(|_| done);
+        if let Some(window) = &mut self.saved_window {
+            window.status = Some(status);
+        }
+    }
+
+    // Saving under an existing name replaces that search but keeps its tab
+    fn save_current_search(&mut self, name: &str) {
+        let pinned = saved::find(&self.settings.saved_searches, name).is_some_and(|s| s.pinned);
+        let search = SavedSearch { pinned, ..self.current_search(name) };
+        self.update_saved_searches(
+            |saved| match saved.iter().position(|s| s.name.eq_ignore_ascii_case(name)) {
+                Some(i) => saved[i] = search,
+                None => saved.push(search),
+            },
+            format!("Saved '{}'", name),
+        );
+    }
 }
 
 // GUI Implementation
 
+impl DeepSearchApp {
+    fn show_catalog_window(&mut self, ctx: &egui::Context) {
+        let Some(window) = &mut self.catalog_window else { return };
+        let mut open = true;
+        let mut save = false;
+        let mut delete = None;
+
+        egui::Window::new("Offline Catalogs").open(&mut open).collapsible(false).show(ctx, |ui| {
+            ui.label("Saved indexes of removable drives, searchable while they are unplugged.");
+            ui.add_space(5.0);
+
+            if self.catalogs.is_empty() {
+                ui.label(egui::RichText::new("No catalogs yet").color(ui.visuals().weak_text_color()));
+            } else {
+                egui::Grid::new("catalogs").striped(true).spacing([12.0, 4.0]).show(ui, |ui| {
+                    for heading in ["Name", "Volume", "Entries", "Saved", "Status", ""] {
+                        ui.label(egui::RichText::new(heading).strong());
+                    }
+                    ui.end_row();
+
+                    for catalog in &self.catalogs {
+                        ui.label(&catalog.name);
+                        ui.label(format!("{} ({})", catalog.label, catalog.serial_text()));
+                        ui.label(catalog.entries.to_string());
+                        ui.label(chrono::DateTime::from_timestamp(catalog.saved, 0)
+                            .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                            .unwrap_or_default());
+                        if self.drive_sources.contains(&DriveSource::Catalog(catalog.serial)) {
+                            ui.label(egui::RichText::new("Offline").color(ui.visuals().warn_fg_color));
+                        } else {
+                            ui.label(egui::RichText::new("Connected").color(theme::success_color(ui.visuals())));
+                        }
+                        if ui.small_button("Delete").clicked() {
+                            delete = Some(catalog.clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+            }
+
+            ui.add_space(5.0);
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Save a catalog of");
+                egui::ComboBox::from_id_salt("catalog_drive")
+                    .selected_text(window.drive.as_deref().unwrap_or("a drive"))
+                    .show_ui(ui, |ui| {
+                        for drive in self.drives.iter().filter(|d| !efu::is_virtual_drive(d)) {
+                            ui.selectable_value(&mut window.drive, Some(drive.clone()), drive);
+                        }
+                    });
+                ui.label("as");
+                ui.add(egui::TextEdit::singleline(&mut window.name).hint_text("Volume label").desired_width(150.0));
+                let can_save = window.drive.is_some() && !window.saving;
+                if ui.add_enabled(can_save, egui::Button::new("Save")).clicked() {
+                    save = true;
+                }
+                if window.saving {
+                    ui.spinner();
+                }
+            });
+            ui.label(egui::RichText::new("Catalogs are refreshed on every scan while their drive is connected.")
+                .small().color(ui.visuals().weak_text_color()));
+
+            match &window.status {
+                Some(Ok(message)) => { ui.label(egui::RichText::new(message).color(theme::success_color(ui.visuals()))); }
+                Some(Err(err)) => { ui.colored_label(ui.visuals().error_fg_color, err); }
+                None => {}
+            }
+        });
+
+        if save {
+            self.start_catalog_save();
+        }
+        if let Some(catalog) = delete {
+            self.delete_catalog(&catalog);
+        }
+        if !open {
+            self.catalog_window = None;
+        }
+    }
+
+    fn show_saved_window(&mut self, ctx: &egui::Context) {
+        let Some(window) = &mut self.saved_window else { return };
+        let mut open = true;
+        let mut save = None;
+        let mut run = None;
+        let mut toggle_pin = None;
+        let mut delete = None;
+
+        egui::Window::new("Saved Searches").open(&mut open).collapsible(false).show(ctx, |ui| {
+            if self.settings.saved_searches.is_empty() {
+                ui.label(egui::RichText::new("No saved searches yet").color(ui.visuals().weak_text_color()));
+            } else {
+                egui::Grid::new("saved_searches").striped(true).spacing([12.0, 4.0]).show(ui, |ui| {
+                    for (i, saved) in self.settings.saved_searches.iter().enumerate() {
+                        ui.label(&saved.name);
+                        let query = saved.request().query;
+                        ui.add(egui::Label::new(egui::RichText::new(query.trim()).small().color(ui.visuals().weak_text_color())).truncate());
+                        let mut pinned = saved.pinned;
+                        if ui.checkbox(&mut pinned, "Tab").on_hover_text("Show as a tab above the results").changed() {
+                            toggle_pin = Some(i);
+                        }
+                        if ui.small_button("Run").clicked() {
+                            run = Some(saved.clone());
+                        }
+                        if ui.small_button("Delete").clicked() {
+                            delete = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+            }
+
+            ui.add_space(5.0);
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Save the current search as");
+                ui.add(egui::TextEdit::singleline(&mut window.name).hint_text("Name").desired_width(150.0));
+                let name = window.name.trim();
+                if ui.add_enabled(!name.is_empty(), egui::Button::new("Save")).clicked() {
+                    save = Some(name.to_string());
+                }
+            });
+            ui.label(egui::RichText::new("Options and sort are saved too. Run one from the command line with: deep_search run <name>")
+                .small().color(ui.visuals().weak_text_color()));
+
+            match &window.status {
+                Some(Ok(message)) => { ui.label(egui::RichText::new(message).color(theme::success_color(ui.visuals()))); }
+                Some(Err(err)) => { ui.colored_label(ui.visuals().error_fg_color, err); }
+                None => {}
+            }
+        });
+
+        if let Some(name) = save {
+            self.save_current_search(&name);
+        }
+        if let Some(i) = toggle_pin {
+            self.update_saved_searches(|saved| saved[i].pinned = !saved[i].pinned, "Saved".to_string());
+        }
+        if let Some(i) = delete {
+            let name = self.settings.saved_searches[i].name.clone();
+            self.update_saved_searches(|saved| { saved.remove(i); }, format!("Deleted '{}'", name));
+        }
+        if let Some(saved) = run {
+            self.apply_saved(&saved);
+        }
+        if !open {
+            self.saved_window = None;
+        }
+    }
+
+    fn show_move_window(&mut self, ctx: &egui::Context) {
+        let Some(window) = &mut self.move_window else { return };
+        let mut open = true;
+        let mut go = false;
+
+        egui::Window::new("Move Selection").open(&mut open).collapsible(false).show(ctx, |ui| {
+            ui.label(format!("Move {} selected items into:", self.selection.len()));
+            let response = ui.add(egui::TextEdit::singleline(&mut window.folder).hint_text("e.g. D:\\Sorted").desired_width(350.0));
+            let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            ui.label(egui::RichText::new("Items already there are left alone, never overwritten.").small().color(ui.visuals().weak_text_color()));
+            ui.add_space(5.0);
+            let can_move = !window.folder.trim().is_empty();
+            if (ui.add_enabled(can_move, egui::Button::new("Move")).clicked() || entered) && can_move {
+                go = true;
+            }
+        });
+
+        if go {
+            let folder = window.folder.clone();
+            self.move_selected(&folder);
+            open = false;
+        }
+        if !open {
+            self.move_window = None;
+        }
+    }
+
+    fn show_trash_confirm(&mut self, ctx: &egui::Context) {
+        let Some(confirm) = &self.trash_confirm else { return };
+        let mut answer = None;
+
+        egui::Window::new("Move to Recycle Bin").collapsible(false).resizable(false).show(ctx, |ui| {
+            let count = confirm.paths.len();
+            ui.label(format!("Move {} item{} to the Recycle Bin?", count, if count == 1 { "" } else { "s" }));
+            for path in confirm.paths.iter().take(10) {
+                ui.label(egui::RichText::new(path).small().color(ui.visuals().weak_text_color()));
+            }
+            if count > 10 {
+                ui.label(egui::RichText::new(format!("…and {} more", count - 10)).small().color(ui.visuals().weak_text_color()));
+            }
+            if confirm.elsewhere > 0 {
+                ui.label(egui::RichText::new(format!("{} selected items aren't on this computer and stay where they are.", confirm.elsewhere))
+                    .small().color(ui.visuals().warn_fg_color));
+            }
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                if ui.add_enabled(count > 0, egui::Button::new("Move to Recycle Bin")).clicked() {
+                    answer = Some(true);
+                }
+                if ui.button("Cancel").clicked() || ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Escape)) {
+                    answer = Some(false);
+                }
+            });
+        });
+
+        if let Some(yes) = answer {
+            let confirm = self.trash_confirm.take();
+            if let Some(confirm) = confirm.filter(|_| yes) {
+                self.trash_selected(&confirm.paths);
+            }
+        }
+    }
+
+    fn show_properties_window(&mut self, ctx: &egui::Context) {
+        let Some(window) = &self.properties_window else { return };
+        let mut open = true;
+        let mut action = None;
+
+        egui::Window::new(format!("Properties of {}", window.entry.name)).id(egui::Id::new("properties")).open(&mut open).collapsible(false).show(ctx, |ui| {
+            egui::Grid::new("properties_grid").num_columns(2).spacing([12.0, 4.0]).show(ui, |ui| {
+                detail_row(ui, "Name", window.entry.name.clone());
+                detail_row(ui, "Location", actions::parent_of(&window.path).to_string());
+                entry_details(ui, &window.entry);
+                detail_row(ui, "From", window.source.clone());
+            });
+
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                if ui.add_enabled(window.local, egui::Button::new("Open")).clicked() {
+                    action = Some(RowAction::Open);
+                }
+                if ui.add_enabled(window.local, egui::Button::new("Show in folder")).clicked() {
+                    action = Some(RowAction::ShowInFolder);
+                }
+                if ui.button("Copy path").clicked() {
+                    ui.ctx().copy_text(window.path.clone());
+                }
+            });
+        });
+
+        if let Some(action) = action {
+            let path = window.path.clone();
+            let result = match action {
+                RowAction::ShowInFolder => shell::show_in_folder(&path),
+                _ => shell::open(&path),
+            };
+            if let Err(e) = result {
+                self.action_status = Some(Err(e));
+            }
+        }
+        if !open {
+            self.properties_window = None;
+        }
+    }
+
+    // Point the preview pane at the current row, asking the worker for its contents when
+    // it is a file on this computer
+    fn update_preview(&mut self) {
+        let entry = self.selected.filter(|_| self.settings.preview).and_then(|row| self.search_results.get(row));
+        let Some(entry) = entry else {
+            self.preview = None;
+            return;
+        };
+        let path = resolve_path(entry, &self.file_data, &self.drives);
+        if self.preview.as_ref().is_some_and(|p| p.path == path) {
+            return;
+        }
+
+        let local = self.is_local(entry);
+        if local && !entry.is_dir {
+            let _ = self.preview_requests.send(path.clone());
+        }
+        self.preview = Some(Preview { entry: entry.clone(), path, local, content: None, texture: None });
+    }
+
+    fn show_preview_pane(&mut self, ctx: &egui::Context) {
+        if !self.settings.preview || !matches!(self.state, AppState::Ready) {
+            return;
+        }
+
+        egui::SidePanel::right("preview").resizable(true).default_width(320.0).width_range(200.0..=800.0).show(ctx, |ui| {
+            let Some(preview) = &self.preview else {
+                ui.add_space(20.0);
+                ui.vertical_centered(|ui| ui.label(egui::RichText::new("Select a result to preview it").color(ui.visuals().weak_text_color())));
+                return;
+            };
+
+            ui.add_space(8.0);
+            ui.add(egui::Label::new(egui::RichText::new(&preview.entry.name).heading()).wrap());
+            ui.add_space(4.0);
+            egui::Grid::new("preview_grid").num_columns(2).spacing([12.0, 4.0]).show(ui, |ui| {
+                detail_row(ui, "Path", preview.path.clone());
+                entry_details(ui, &preview.entry);
+            });
+            ui.separator();
+
+            let note = |ui: &mut egui::Ui, text: &str| {
+                ui.label(egui::RichText::new(text).small().color(ui.visuals().weak_text_color()));
+            };
+            if preview.entry.is_dir {
+                return;
+            }
+            if !preview.local {
+                note(ui, "Not on this computer, so there is nothing to preview.");
+                return;
+            }
+            match &preview.content {
+                None => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        note(ui, "Loading preview…");
+                    });
+                }
+                Some(Err(e)) => note(ui, e),
+                Some(Ok(Content::Binary)) => note(ui, "No preview for this type of file."),
+                Some(Ok(Content::Image { width, height, .. })) => {
+                    note(ui, &format!("{} × {} pixels", width, height));
+                    if let Some(texture) = &preview.texture {
+                        ui.add(egui::Image::new(texture).max_width(ui.available_width()).shrink_to_fit());
+                    }
+                }
+                Some(Ok(Content::Text { text, encoding, truncated })) => {
+                    if *truncated {
+                        note(ui, &format!("{}, first {}", encoding, format::human_size(preview::TEXT_BYTES as u64)));
+                    } else {
+                        note(ui, encoding);
+                    }
+                    egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                        ui.add(egui::Label::new(egui::RichText::new(text).monospace()).extend());
+                    });
+                }
+            }
+        });
+    }
+
+    // Ask first; nothing is deleted until the confirmation says so
+    fn ask_trash(&mut self) {
+        let (paths, elsewhere) = self.selected_local_paths();
+        if paths.is_empty() && elsewhere == 0 {
+            return;
+        }
+        self.trash_confirm = Some(TrashConfirm { paths, elsewhere });
+    }
+
+    fn show_file_lists_window(&mut self, ctx: &egui::Context) {
+        let Some(window) = &mut self.file_lists_window else { return };
+        let mut open = true;
+        let mut lists = None;
+        let mut export = false;
+
+        egui::Window::new("File Lists").open(&mut open).collapsible(false).show(ctx, |ui| {
+            ui.label("EFU file lists, searched as read-only drives next to this machine's:");
+            let loaded = &self.settings.file_lists;
+            if loaded.is_empty() {
+                ui.label(egui::RichText::new("None loaded").color(ui.visuals().weak_text_color()));
+            }
+            for (i, list) in loaded.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                        let mut remaining = loaded.clone();
+                        remaining.remove(i);
+                        lists = Some(remaining);
+                    }
+                    ui.label(list);
+                });
+            }
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut window.new_list).hint_text("Path to a .efu file").desired_width(300.0));
+                let path = window.new_list.trim();
+                if ui.add_enabled(!path.is_empty(), egui::Button::new("Add")).clicked() {
+                    if std::path::Path::new(path).is_file() {
+                        lists = Some(loaded.iter().cloned().chain(Some(path.to_string())).collect());
+                        window.new_list.clear();
+                    } else {
+                        window.status = Some(Err(format!("{} is not a file", path)));
+                    }
+                }
+            });
+
+            ui.add_space(5.0);
+            ui.separator();
+            ui.label("Export this machine's index as a file list:");
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut window.export_path).desired_width(300.0));
+                let can_export = !window.exporting && !window.export_path.trim().is_empty();
+                if ui.add_enabled(can_export, egui::Button::new("Export")).clicked() {
+                    export = true;
+                }
+                if window.exporting {
+                    ui.spinner();
+                }
+            });
+
+            match &window.status {
+                Some(Ok(message)) => { ui.label(egui::RichText::new(message).color(theme::success_color(ui.visuals()))); }
+                Some(Err(err)) => { ui.colored_label(ui.visuals().error_fg_color, err); }
+                None => {}
+            }
+        });
+
+        if let Some(lists) = lists {
+            self.set_file_lists(lists);
+        }
+        if export {
+            self.start_index_export();
+        }
+        if !open {
+            self.file_lists_window = None;
+        }
+    }
+
+    fn show_export_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.export_dialog else { return };
+        let mut open = true;
+        let mut export = false;
+
+        egui::Window::new("Export Results").open(&mut open).collapsible(false).show(ctx, |ui| {
+            ui.label(match (&dialog.selection, self.search_stats) {
+                (Some(selection), _) => format!("{} selected results", selection.len()),
+                (None, Some((count, _))) => format!("{} results", count),
+                (None, None) => format!("{}+ results", self.search_results.len()),
+            });
+            ui.add_space(5.0);
+
+            ui.horizontal(|ui| {
+                ui.label("Format:");
+                let before = dialog.format;
+                egui::ComboBox::from_id_salt("export_format")
+                    .selected_text(dialog.format.label())
+                    .show_ui(ui, |ui| {
+                        for format in ExportFormat::ALL {
+                            ui.selectable_value(&mut dialog.format, format, format.label());
+                        }
+                    });
+                // Keep the file extension in step with the format
+                if dialog.format != before {
+                    let mut path = PathBuf::from(&dialog.path);
+                    path.set_extension(dialog.format.extension());
+                    dialog.path = path.display().to_string();
+                }
+            });
+
+            ui.add_enabled_ui(dialog.format != ExportFormat::Paths, |ui| {
+                ui.label("Columns:");
+                ui.horizontal_wrapped(|ui| {
+                    for column in Column::ALL {
+                        let mut checked = dialog.columns.contains(&column);
+                        if ui.checkbox(&mut checked, column.key()).changed() {
+                            // Rebuild from ALL so the column order stays fixed
+                            let selected: Vec<Column> = Column::ALL.into_iter()
+                                .filter(|c| if *c == column { checked } else { dialog.columns.contains(c) })
+                                .collect();
+                            dialog.columns = selected;
+                        }
+                    }
+                });
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("File:");
+                ui.add(egui::TextEdit::singleline(&mut dialog.path).desired_width(350.0));
+            });
+
+            match &dialog.status {
+                Some(Ok(message)) => { ui.label(egui::RichText::new(message).color(theme::success_color(ui.visuals()))); }
+                Some(Err(err)) => { ui.colored_label(ui.visuals().error_fg_color, err); }
+                None => {}
+            }
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                let can_export = !dialog.running && (dialog.format == ExportFormat::Paths || !dialog.columns.is_empty());
+                if ui.add_enabled(can_export, egui::Button::new("Export")).clicked() {
+                    export = true;
+                }
+                if dialog.running {
+                    ui.spinner();
+                }
+            });
+        });
+
+        if export {
+            self.start_export();
+        }
+        if !open {
+            self.export_dialog = None;
+        }
+    }
+
+    fn show_settings_window(&mut self, ctx: &egui::Context) {
+        let Some(window) = &mut self.settings_window else { return };
+        let mut open = true;
+        let mut save = false;
+        let draft = &mut window.draft;
+
+        egui::Window::new("Settings").open(&mut open).collapsible(false).vscroll(true).show(ctx, |ui| {
+            let note = |ui: &mut egui::Ui, text: &str| {
+                ui.label(egui::RichText::new(text).small().color(ui.visuals().weak_text_color()));
+            };
+
+            ui.heading("Appearance");
+            theme::controls(ui, &mut draft.theme);
+            ui.checkbox(&mut draft.preview, "Show the preview pane");
+            ui.add_space(5.0);
+
+            ui.heading("Window");
+            ui.horizontal(|ui| {
+                ui.label("Size at startup:");
+                ui.add(egui::DragValue::new(&mut draft.window.width).range(settings::WINDOW_SIZE).suffix(" wide"));
+                ui.add(egui::DragValue::new(&mut draft.window.height).range(settings::WINDOW_SIZE).suffix(" high"));
+                let current = ui.button("Use current size").clicked().then(|| ui.input(|i| i.viewport().inner_rect));
+                if let Some(rect) = current.flatten() {
+                    draft.window.width = rect.width().round();
+                    draft.window.height = rect.height().round();
+                }
+            });
+            ui.add_space(5.0);
+
+            ui.heading("Scanning");
+            egui::Grid::new("scan_settings").num_columns(2).show(ui, |ui| {
+                ui.label("Most entries per drive:");
+                ui.add(egui::DragValue::new(&mut draft.scan.max_entries).range(settings::MAX_ENTRIES).speed(10_000));
+                ui.end_row();
+                ui.label("MFT read buffer:");
+                ui.add(egui::DragValue::new(&mut draft.scan.buffer_kb).range(settings::BUFFER_KB).suffix(" KB"));
+                ui.end_row();
+            });
+            ui.checkbox(&mut draft.scan.read_metadata, "Read sizes and dates (slower scans)");
+            note(ui, "Used from the next scan.");
+            ui.add_space(5.0);
+
+            ui.heading("Search history");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut draft.history.enabled, "Remember searches, up to");
+                ui.add_enabled(draft.history.enabled, egui::DragValue::new(&mut draft.history.limit).range(settings::HISTORY_LIMIT));
+            });
+            ui.add_space(5.0);
+
+            ui.heading("HTTP server");
+            ui.checkbox(&mut draft.server.enabled, "Serve searches on 127.0.0.1");
+            egui::Grid::new("server_settings").num_columns(2).show(ui, |ui| {
+                ui.label("Port:");
+                ui.add(egui::DragValue::new(&mut draft.server.port).range(1..=u16::MAX));
+                ui.end_row();
+                ui.label("Username:");
+                ui.add(egui::TextEdit::singleline(&mut draft.server.username).hint_text("none, no login").desired_width(150.0));
+                ui.end_row();
+                ui.label("Password:");
+                ui.add(egui::TextEdit::singleline(&mut draft.server.password).password(true).desired_width(150.0));
+                ui.end_row();
+            });
+            note(ui, "Server changes take effect the next time the app starts.");
+
+            ui.add_space(5.0);
+            match &window.status {
+                Some(Ok(message)) => { ui.label(egui::RichText::new(message).color(theme::success_color(ui.visuals()))); }
+                Some(Err(err)) => { ui.colored_label(ui.visuals().error_fg_color, err); }
+                None => {}
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    save = true;
+                }
+                if ui.button("Reset to defaults").on_hover_text("Everything on this page; searches, lists and rules are kept").clicked() {
+                    let defaults = Settings::default();
+                    draft.theme = defaults.theme;
+                    draft.preview = defaults.preview;
+                    draft.window = defaults.window;
+                    draft.scan = ScanSettings { skip_drives: draft.scan.skip_drives.clone(), ..defaults.scan };
+                    draft.history = defaults.history;
+                    draft.server = defaults.server;
+                }
+                let path = Settings::path().filter(|p| p.exists());
+                let clicked = ui.add_enabled(path.is_some(), egui::Button::new("Open settings file")).clicked();
+                if let Some(Err(e)) = path.filter(|_| clicked).map(|p| shell::open(&p.display().to_string())) {
+                    window.status = Some(Err(e));
+                }
+            });
+        });
+
+        if save {
+            window.status = Some(match window.draft.validate() {
+                // Only what the window shows; the rest may have changed meanwhile
+                Ok(()) => {
+                    let draft = window.draft.clone();
+                    self.settings.theme = draft.theme;
+                    self.settings.preview = draft.preview;
+                    self.settings.window = draft.window;
+                    // Which drives to skip is the drives window's business
+                    self.settings.scan = ScanSettings { skip_drives: self.settings.scan.skip_drives.clone(), ..draft.scan };
+                    self.settings.history = draft.history;
+                    self.settings.server = draft.server;
+                    self.settings.save().map(|()| "Saved".to_string())
+                }
+                Err(e) => Err(e),
+            });
+        }
+        if !open {
+            self.settings_window = None;
+        }
+    }
+
+    fn show_exclude_editor(&mut self, ctx: &egui::Context) {
+        let Some(editor) = &mut self.exclude_editor else { return };
+        let mut open = true;
+        let mut save = false;
+        let mut switch_to = None;
+
+        egui::Window::new("Exclusions").open(&mut open).collapsible(false).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Rules for:");
+                egui::ComboBox::from_id_salt("exclude_drive")
+                    .selected_text(editor.drive.as_deref().unwrap_or("All drives"))
+                    .show_ui(ui, |ui| {
+                        if ui.selectable_label(editor.drive.is_none(), "All drives").clicked() {
+                            switch_to = Some(None);
+                        }
+                        for drive in self.drives.iter() {
+                            if ui.selectable_label(editor.drive.as_ref() == Some(drive), drive).clicked() {
+                                switch_to = Some(Some(drive.clone()));
+                            }
+                        }
+                    });
+            });
+            ui.add_space(5.0);
+
+            ui.label("Folders (full paths, one per line):");
+            ui.add(egui::TextEdit::multiline(&mut editor.folders).desired_rows(3).desired_width(f32::INFINITY));
+            ui.label("Name patterns (* and ?, use \\ to match parent folders too):");
+            ui.add(egui::TextEdit::multiline(&mut editor.patterns).desired_rows(5).desired_width(f32::INFINITY));
+            ui.horizontal(|ui| {
+                ui.label("Attributes:");
+                ui.add(egui::TextEdit::singleline(&mut editor.attributes).hint_text("e.g. T").desired_width(60.0));
+            });
+
+            if let Some(err) = &editor.error {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+            }
+            ui.add_space(5.0);
+            if ui.button("Save").clicked() {
+                save = true;
+            }
+        });
+
+        if save {
+            self.save_exclude_editor();
+        }
+        if let Some(drive) = switch_to {
+            self.exclude_editor = Some(ExcludeEditor::load(&self.settings, drive));
+        }
+        if !open {
+            self.exclude_editor = None;
+        }
+    }
+}
+
 impl eframe::App for DeepSearchApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Set a custom theme
-        let mut visuals = egui::Visuals::dark();
-        visuals.window_fill = egui::Color32::from_rgb(30, 30, 35); // Dark blue-ish grey
-        visuals.panel_fill = egui::Color32::from_rgb(30, 30, 35);
-        visuals.widgets.noninteractive.bg_fill = egui::Color32::from_rgb(30, 30, 35);
-        ctx.set_visuals(visuals);
+        // The theme is handed to egui once, and again whenever it is changed
+        if self.applied_theme.as_ref() != Some(&self.settings.theme) {
+            if let Err(e) = theme::apply(ctx, &self.settings.theme) {
+                self.settings_error = Some(e);
+            }
+            self.applied_theme = Some(self.settings.theme.clone());
+        }
 
         // Handle async messages
         while let Ok((count, current_drive)) = self.rx_progress.try_recv() {
@@ -222,24 +1841,131 @@ impl eframe::App for DeepSearchApp {
                 *d = current_drive;
             }
         }
-        if let Ok((data, drives, errors)) = self.rx_data.try_recv() {
-            self.file_data = Arc::new(data);
-            self.drives = Arc::new(drives);
-            self.scan_errors = errors;
-            self.state = AppState::Ready;
+        if let Ok(result) = self.rx_data.try_recv() {
+            self.file_data = Arc::new(result.entries);
+            self.folders = Arc::new(result.folders);
+            self.drives = Arc::new(result.drives);
+            self.scan_errors = result.errors;
+            self.drive_status = result.statuses;
+            self.drive_sources = result.sources;
+            self.state = AppState::Ready;
+            self.focus_search = true;
+            self.reload_catalogs();
+            self.publish_index();
+            self.rebuild_exclusions();
+        }
+        while let Ok(result) = self.rx_exclusions.try_recv() {
+            match result {
+                Ok(set) => self.exclusions = Some(Arc::new(set)),
+                Err(e) => self.settings_error = Some(format!("Exclusion rules: {}", e)),
+            }
+            self.publish_index();
+            self.perform_search();
+        }
+        if let Ok(err) = self.rx_error.try_recv() {
+            self.state = AppState::Error(err);
+        }
+        while let Ok(result) = self.rx_catalog.try_recv() {
+            let message = result.map(|c| format!("Saved {} entries as \"{}\"", c.entries, c.name));
+            if let Some(window) = &mut self.catalog_window {
+                window.saving = false;
+                window.status = Some(message);
+            }
+            self.reload_catalogs();
+        }
+        while let Ok(result) = self.rx_index_export.try_recv() {
+            if let Some(window) = &mut self.file_lists_window {
+                window.exporting = false;
+                window.status = Some(result.map(|(rows, path)| format!("Exported {} entries to {}", rows, path.display())));
+            }
+        }
+        while let Ok(result) = self.rx_export.try_recv() {
+            if let Some(dialog) = &mut self.export_dialog {
+                dialog.running = false;
+                dialog.status = Some(result.map(|(rows, path)| format!("Exported {} results to {}", rows, path.display())));
+            }
         }
-        if let Ok(err) = self.rx_error.try_recv() {
-            self.state = AppState::Error(err);
+        while let Ok(rescanned) = self.rx_rescan.try_recv() {
+            self.rescanning = None;
+            let status = rescanned.status;
+            if let Some(old) = self.drive_status.iter_mut().find(|s| s.drive == status.drive) {
+                *old = status;
+            }
+            // A full scan finished meanwhile has newer entries for the drive anyway
+            if let Some((entries, folders)) = rescanned.entries.filter(|_| Arc::ptr_eq(&rescanned.base, &self.file_data)) {
+                self.file_data = Arc::new(entries);
+                self.folders = Arc::new(folders);
+                self.publish_index();
+                self.rebuild_exclusions(); // Searches again once done
+            }
+        }
+        while let Ok(relisted) = self.rx_relist.try_recv() {
+            // The index changed under it, so the lists are read again with everything else
+            if !Arc::ptr_eq(&relisted.base, &self.file_data) {
+                self.start_scan();
+                continue;
+            }
+            self.file_data = Arc::new(relisted.entries);
+            self.folders = Arc::new(relisted.folders);
+            self.drives = Arc::new(relisted.drives);
+            self.drive_sources = relisted.sources;
+            if let Some(window) = &mut self.file_lists_window {
+                window.status = Some(if relisted.errors.is_empty() {
+                    Ok("File lists updated".to_string())
+                } else {
+                    Err(relisted.errors.join("\n"))
+                });
+            }
+            self.publish_index();
+            self.rebuild_exclusions(); // Searches again once done
+        }
+        while let Ok((path, content)) = self.rx_preview.try_recv() {
+            // Contents of a row already moved away from are dropped
+            if let Some(preview) = self.preview.as_mut().filter(|p| p.path == path) {
+                if let Ok(Content::Image { thumbnail, .. }) = &content {
+                    preview.texture = Some(ctx.load_texture("preview", thumbnail.clone(), egui::TextureOptions::LINEAR));
+                }
+                preview.content = Some(content);
+            }
         }
         
         // Handle search results
-        while let Ok((query, results, duration)) = self.rx_search.try_recv() {
-            // Only update if the result matches the current query (ignore old results)
-            if query == self.search_query {
-                self.search_stats = Some((results.len(), duration));
-                self.search_results = results;
+        while let Ok((id, update)) = self.rx_search.try_recv() {
+            // Only update if the result belongs to the current search (ignore old results)
+            if id != self.search_id {
+                continue;
+            }
+            match update {
+                SearchUpdate::Page(page, more) => {
+                    // Pages after the first are only sent when asked for, so anything
+                    // arriving unasked is a first page and replaces the old results
+                    if !self.loading_more {
+                        self.search_results.clear();
+                        self.clear_selection();
+                    }
+                    self.search_results.extend(page);
+                    self.loading_more = false;
+                    if !more {
+                        self.search_more = None;
+                    }
+                }
+                SearchUpdate::Total(count, duration) => self.search_stats = Some((count, duration)),
+                SearchUpdate::Error(err) => {
+                    self.search_error = Some(err);
+                    self.search_results.clear();
+                    self.clear_selection();
+                    self.search_stats = None;
+                    self.search_more = None;
+                }
+            }
+        }
+
+        // A query that has stayed put for a moment counts as used
+        if let Some(changed_at) = self.history_pending {
+            match HISTORY_DELAY.checked_sub(changed_at.elapsed()) {
+                Some(left) => ctx.request_repaint_after(left),
+                None => self.record_history(),
             }
-            
         }
 
         // Auto-start scan on first frame
@@ -247,6 +1973,19 @@ impl eframe::App for DeepSearchApp {
             self.start_scan();
         }
 
+        self.show_exclude_editor(ctx);
+        self.show_export_dialog(ctx);
+        self.show_file_lists_window(ctx);
+        self.show_catalog_window(ctx);
+        self.show_saved_window(ctx);
+        self.show_move_window(ctx);
+        self.show_trash_confirm(ctx);
+        self.show_properties_window(ctx);
+        self.show_settings_window(ctx);
+        self.show_drives_window(ctx);
+        self.update_preview();
+        self.show_preview_pane(ctx);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             // Disable text selection for labels to prevent cursor changing to I-beam
             ui.style_mut().interaction.selectable_labels = false;
@@ -271,7 +2010,7 @@ impl eframe::App for DeepSearchApp {
                     ctx.request_repaint(); // Animate spinner
                 }
                 AppState::Error(msg) => {
-                    ui.colored_label(egui::Color32::RED, format!("Error: {}", msg));
+                    ui.colored_label(ui.visuals().error_fg_color, format!("Error: {}", msg));
                     if ui.button("Retry").clicked() {
                         self.start_scan();
                     }
@@ -280,89 +2019,516 @@ impl eframe::App for DeepSearchApp {
                     ui.vertical_centered(|ui| {
                         ui.add_space(20.0);
                         ui.heading("Deep Search");
-                        ui.add_space(5.0);
-                        ui.label(egui::RichText::new("Shows hidden/system files").size(10.0).color(egui::Color32::GRAY));
                     });
                     
-                    if !self.scan_errors.is_empty() {
+                    // Each problem under the part of the app it comes from
+                    let sources = [
+                        ("Settings", &self.settings_error),
+                        ("Catalogs", &self.catalog_error),
+                        ("HTTP server", &self.server_error),
+                        ("Command-line endpoint", &self.ipc_error),
+                        ("Search history", &self.history_error),
+                    ];
+                    let warnings: Vec<(&str, &String)> = self.scan_errors.iter().map(|e| ("Scan", e))
+                        .chain(sources.iter().filter_map(|(source, error)| error.as_ref().map(|e| (*source, e))))
+                        .collect();
+                    if !warnings.is_empty() {
                         ui.group(|ui| {
                             ui.set_max_width(f32::INFINITY);
-                            ui.colored_label(egui::Color32::YELLOW, "⚠️ Scan Warnings:");
-                            for err in &self.scan_errors {
-                                ui.label(egui::RichText::new(err).size(11.0).color(egui::Color32::LIGHT_RED));
+                            ui.colored_label(ui.visuals().warn_fg_color, "⚠️ Warnings:");
+                            for (source, err) in warnings {
+                                ui.label(egui::RichText::new(format!("{}: {}", source, err)).small().color(ui.visuals().error_fg_color));
                             }
                         });
                     }
 
                     ui.add_space(10.0);
-                    
+
+                    // Keyboard navigation. The results have the keys once entered with Down
+                    // from the search box or by clicking a row, until a text box takes focus.
+                    let search_id = egui::Id::new("search_box");
+                    if std::mem::take(&mut self.focus_search) {
+                        ui.memory_mut(|m| m.request_focus(search_id));
+                    }
+                    if ui.memory(|m| m.focused().is_some()) {
+                        self.results_focused = false;
+                    }
+                    // egui has already taken focus away from the search box for Esc
+                    let escape = ui.input(|i| i.key_pressed(egui::Key::Escape));
+                    if escape && (self.results_focused || ui.memory(|m| m.had_focus_last_frame(search_id))) {
+                        self.clear_query();
+                        self.results_focused = false;
+                        ui.memory_mut(|m| {
+                            m.close_popup();
+                            m.request_focus(search_id);
+                        });
+                    }
+                    let dialog_open = self.move_window.is_some() || self.trash_confirm.is_some();
+                    if self.results_focused && !dialog_open && !self.search_results.is_empty() {
+                        let (up, down, page_up, page_down, home, end) = ui.input_mut(|i| {
+                            let mut key = |key| i.consume_key(egui::Modifiers::NONE, key);
+                            (
+                                key(egui::Key::ArrowUp),
+                                key(egui::Key::ArrowDown),
+                                key(egui::Key::PageUp),
+                                key(egui::Key::PageDown),
+                                key(egui::Key::Home),
+                                key(egui::Key::End),
+                            )
+                        });
+                        let (open_folder, open, copy, select_all, delete) = ui.input_mut(|i| {
+                            (
+                                i.consume_key(egui::Modifiers::COMMAND, egui::Key::Enter),
+                                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                                i.consume_key(egui::Modifiers::COMMAND, egui::Key::C) | i.events.iter().any(|e| matches!(e, egui::Event::Copy)),
+                                i.consume_key(egui::Modifiers::COMMAND, egui::Key::A),
+                                i.consume_key(egui::Modifiers::NONE, egui::Key::Delete),
+                            )
+                        });
+                        // Shift+arrows stretch the selection
+                        let extend = ui.input(|i| i.modifiers.shift);
+                        let page = self.visible_rows.len().saturating_sub(2).max(1) as isize;
+
+                        if up && self.selected == Some(0) && !extend {
+                            // Up from the top row goes back to typing
+                            self.results_focused = false;
+                            ui.memory_mut(|m| m.request_focus(search_id));
+                        } else if up {
+                            self.step_selection(-1, extend);
+                        } else if down {
+                            self.step_selection(1, extend);
+                        } else if page_up {
+                            self.step_selection(-page, extend);
+                        } else if page_down {
+                            self.step_selection(page, extend);
+                        } else if home {
+                            self.step_selection(isize::MIN, extend);
+                        } else if end {
+                            self.step_selection(isize::MAX, extend);
+                        }
+
+                        if open_folder || open {
+                            self.open_selected(open_folder);
+                        }
+                        if select_all {
+                            self.select_all();
+                        }
+                        if copy {
+                            self.copy_selection(ui.ctx(), false);
+                        }
+                        if delete {
+                            self.ask_trash();
+                        }
+                    }
+
                     // Search Bar
                     ui.horizontal(|ui| {
                         ui.add_space(20.0);
+                        let filter_width = 130.0;
+                        let popup_id = search_id.with("suggestions");
+
+                        // Down with no earlier search recalled moves into the results
+                        if ui.memory(|m| m.has_focus(search_id)) && self.history_pos.is_none() && !self.search_results.is_empty()
+                            && ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown))
+                        {
+                            self.results_focused = true;
+                            if self.selected.is_none() {
+                                self.step_selection(0, false);
+                            }
+                            ui.memory_mut(|m| {
+                                m.surrender_focus(search_id);
+                                m.close_popup();
+                            });
+                        }
+
+                        // Up/down recall earlier searches, taken before the text box sees them
+                        if self.settings.history.enabled && ui.memory(|m| m.has_focus(search_id)) {
+                            let (up, down) = ui.input_mut(|i| {
+                                (i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp), i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown))
+                            });
+                            if up || down {
+                                self.recall_history(up);
+                                ui.memory_mut(|m| m.close_popup());
+                                if let Some(mut state) = egui::TextEdit::load_state(ui.ctx(), search_id) {
+                                    let end = egui::text::CCursor::new(self.search_query.chars().count());
+                                    state.cursor.set_char_range(Some(egui::text::CCursorRange::one(end)));
+                                    state.store(ui.ctx(), search_id);
+                                }
+                            }
+                        }
+
                         let response = ui.add(
                             egui::TextEdit::singleline(&mut self.search_query)
-                                .hint_text("Type to search...")
-                                .desired_width(f32::INFINITY)
+                                .id(search_id)
+                                .hint_text("Type to search... (ext:pdf, type:images)")
+                                .desired_width(ui.available_width() - filter_width - 30.0)
                                 .min_size(egui::vec2(0.0, 30.0)) // Taller
                         );
+                        let mut changed = response.changed();
+                        if changed {
+                            self.history_pos = None;
+                            self.history_pending = Some(Instant::now());
+                        }
+                        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            self.record_history();
+                            ui.memory_mut(|m| m.close_popup());
+                        }
+
+                        // Earlier searches matching what has been typed
+                        if self.settings.history.enabled && response.has_focus() && (changed || response.gained_focus()) {
+                            ui.memory_mut(|m| m.open_popup(popup_id));
+                        }
+                        if ui.memory(|m| m.is_popup_open(popup_id)) {
+                            let now = chrono::Utc::now().timestamp();
+                            let suggestions: Vec<String> = self.history.suggestions(&self.search_query, now, SUGGESTIONS)
+                                .into_iter()
+                                .map(str::to_string)
+                                .collect();
+                            if suggestions.is_empty() {
+                                ui.memory_mut(|m| m.close_popup());
+                            }
+                            let mut picked = None;
+                            egui::popup_below_widget(ui, popup_id, &response, egui::PopupCloseBehavior::CloseOnClickOutside, |ui| {
+                                ui.set_min_width(response.rect.width());
+                                for suggestion in suggestions {
+                                    if ui.selectable_label(false, &suggestion).clicked() {
+                                        picked = Some(suggestion);
+                                    }
+                                }
+                            });
+                            if let Some(query) = picked {
+                                ui.memory_mut(|m| m.close_popup());
+                                self.search_query = query;
+                                self.history_pending = Some(Instant::now());
+                                changed = true;
+                            }
+                        }
+
+                        // File type dropdown
+                        let selected = self.type_filter
+                            .and_then(|i| self.categories.get(i))
+                            .map_or("All types", |c| c.name.as_str());
+                        egui::ComboBox::from_id_salt("type_filter")
+                            .width(filter_width)
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                changed |= ui.selectable_value(&mut self.type_filter, None, "All types").changed();
+                                for (i, category) in self.categories.iter().enumerate() {
+                                    changed |= ui.selectable_value(&mut self.type_filter, Some(i), &category.name).changed();
+                                }
+                            });
+
+                        if changed {
+                            self.perform_search();
+                        }
+                        ui.add_space(20.0);
+                    });
+
+                    // Search options
+                    ui.horizontal(|ui| {
+                        ui.add_space(20.0);
+                        let mut regex = self.match_mode == MatchMode::Regex;
+                        if ui.checkbox(&mut regex, "Regex").on_hover_text("Match names with a regular expression").changed() {
+                            self.match_mode = if regex { MatchMode::Regex } else { MatchMode::Prefix };
+                            self.perform_search();
+                        }
+                        if ui.checkbox(&mut self.hide_hidden, "Hide hidden/system files").changed() {
+                            self.perform_search();
+                        }
+
+                        ui.add_space(10.0);
+                        ui.label("In folder:");
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut self.search_folder)
+                                .hint_text("e.g. D:\\repos")
+                                .desired_width(ui.available_width() - 50.0)
+                        );
                         if response.changed() {
                             self.perform_search();
                         }
+                        if !self.search_folder.is_empty() && ui.small_button("✖").on_hover_text("Search everywhere").clicked() {
+                            self.search_folder.clear();
+                            self.perform_search();
+                        }
+                    });
+                    ui.horizontal(|ui| {
                         ui.add_space(20.0);
+                        if ui.checkbox(&mut self.include_excluded, "Include excluded items").changed() {
+                            self.perform_search();
+                        }
+
+                        ui.add_space(10.0);
+                        ui.label("Sort:");
+                        let mut changed = false;
+                        egui::ComboBox::from_id_salt("sort")
+                            .width(110.0)
+                            .selected_text(self.sort.map_or("Index order", SortKey::label))
+                            .show_ui(ui, |ui| {
+                                changed |= ui.selectable_value(&mut self.sort, None, "Index order").changed();
+                                for key in SortKey::ALL {
+                                    changed |= ui.selectable_value(&mut self.sort, Some(key), key.label()).changed();
+                                }
+                            });
+                        changed |= ui.toggle_value(&mut self.sort_reverse, "⇅").on_hover_text("Reverse the order").changed();
+                        if changed {
+                            self.perform_search();
+                        }
+                        ui.add_space(10.0);
+
+                        if ui.button("Exclusions…").clicked() && self.exclude_editor.is_none() {
+                            self.exclude_editor = Some(ExcludeEditor::load(&self.settings, None));
+                        }
+                        if ui.button("Drives…").clicked() && self.drives_window.is_none() {
+                            self.drives_window = Some(DrivesWindow { skip: self.settings.scan.skip_drives.clone() });
+                        }
+                        if ui.button("File Lists…").clicked() && self.file_lists_window.is_none() {
+                            self.file_lists_window = Some(FileListsWindow::new());
+                        }
+                        if ui.button("Catalogs…").clicked() && self.catalog_window.is_none() {
+                            self.catalog_window = Some(CatalogWindow { drive: None, name: String::new(), saving: false, status: None });
+                        }
+                        if ui.toggle_value(&mut self.settings.preview, "Preview").on_hover_text("Show the current result's details and contents beside the list").changed() {
+                            self.settings_error = self.settings.save().err();
+                        }
+
+                        // Changes show at once (see update) and are saved once settled
+                        let theme_menu = ui.menu_button("Theme ▾", |ui| theme::controls(ui, &mut self.settings.theme));
+                        if theme_menu.inner == Some(true) {
+                            self.settings_error = self.settings.save().err();
+                        }
+                        if ui.button("Settings…").clicked() && self.settings_window.is_none() {
+                            self.settings_window = Some(SettingsWindow { draft: self.settings.clone(), status: None });
+                        }
+
+                        let mut run = None;
+                        let mut remember = self.settings.history.enabled;
+                        let mut clear_history = false;
+                        ui.menu_button("Saved ▾", |ui| {
+                            for saved in &self.settings.saved_searches {
+                                if ui.button(&saved.name).clicked() {
+                                    run = Some(saved.clone());
+                                    ui.close_menu();
+                                }
+                            }
+                            if !self.settings.saved_searches.is_empty() {
+                                ui.separator();
+                            }
+                            if ui.button("Save / Manage…").clicked() {
+                                if self.saved_window.is_none() {
+                                    self.saved_window = Some(SavedWindow { name: String::new(), status: None });
+                                }
+                                ui.close_menu();
+                            }
+
+                            ui.separator();
+                            if ui.checkbox(&mut remember, "Remember searches").changed() {
+                                self.settings.history.enabled = remember;
+                                self.settings_error = self.settings.save().err();
+                            }
+                            if ui.add_enabled(!self.history.is_empty(), egui::Button::new("Clear search history")).clicked() {
+                                clear_history = true;
+                                ui.close_menu();
+                            }
+                        });
+                        if let Some(saved) = run {
+                            self.apply_saved(&saved);
+                        }
+                        if clear_history {
+                            self.clear_history();
+                        }
                     });
 
+                    // Pinned saved searches as tabs
+                    let pinned: Vec<SavedSearch> = self.settings.saved_searches.iter().filter(|s| s.pinned).cloned().collect();
+                    if !pinned.is_empty() {
+                        ui.horizontal(|ui| {
+                            ui.add_space(20.0);
+                            for saved in &pinned {
+                                if ui.selectable_label(self.is_current(saved), &saved.name).clicked() {
+                                    self.apply_saved(saved);
+                                }
+                            }
+                        });
+                    }
+
+                    if let Some(err) = &self.search_error {
+                        ui.horizontal(|ui| {
+                            ui.add_space(25.0);
+                            ui.label(egui::RichText::new(err).small().color(ui.visuals().error_fg_color));
+                        });
+                    }
+
                     // Stats
-                    if let Some((count, duration)) = self.search_stats {
-                        if count > 0 {
+                    let summary = match self.search_stats {
+                        Some((0, _)) => None,
+                        Some((count, duration)) => Some(format!("Found {} results in {:.3}s", count, duration.as_secs_f32())),
+                        // Still counting, so only what has been loaded is known
+                        None if self.search_results.is_empty() => None,
+                        None if self.search_more.is_some() => Some(format!("{}+ results, counting…", self.search_results.len())),
+                        None => Some(format!("{} results", self.search_results.len())),
+                    };
+                    if let Some(summary) = summary {
+                        ui.horizontal(|ui| {
+                            ui.add_space(25.0);
+                            ui.label(egui::RichText::new(summary).small().color(ui.visuals().weak_text_color()));
+                            if ui.small_button("Export…").clicked() && self.export_dialog.is_none() {
+                                self.export_dialog = Some(ExportDialog::new());
+                            }
+
+                            // Bulk actions on the selected rows
+                            if !self.selection.is_empty() {
+                                ui.add_space(10.0);
+                                ui.label(egui::RichText::new(format!("{} selected", self.selection.len())).small().color(ui.visuals().hyperlink_color));
+                                ui.menu_button("Selection ▾", |ui| {
+                                    if ui.button("Copy paths").clicked() {
+                                        self.copy_selection(ui.ctx(), false);
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Copy names").clicked() {
+                                        self.copy_selection(ui.ctx(), true);
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Export selection…").clicked() {
+                                        self.export_selection();
+                                        ui.close_menu();
+                                    }
+                                    ui.separator();
+                                    if ui.button("Move to folder…").clicked() {
+                                        self.move_window.get_or_insert_with(|| MoveWindow { folder: String::new() });
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Move to Recycle Bin…").clicked() {
+                                        self.ask_trash();
+                                        ui.close_menu();
+                                    }
+                                    ui.separator();
+                                    if ui.button("Select all loaded").clicked() {
+                                        self.select_all();
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Clear selection").clicked() {
+                                        self.clear_selection();
+                                        ui.close_menu();
+                                    }
+                                });
+                            }
+                        });
+                    }
+                    match &self.action_status {
+                        Some(Ok(message)) => {
+                            ui.horizontal(|ui| {
+                                ui.add_space(25.0);
+                                ui.label(egui::RichText::new(message).small().color(theme::success_color(ui.visuals())));
+                            });
+                        }
+                        Some(Err(err)) => {
                             ui.horizontal(|ui| {
                                 ui.add_space(25.0);
-                                ui.label(egui::RichText::new(format!(
-                                    "Found {} results in {:.3}s", 
-                                    count, 
-                                    duration.as_secs_f32()
-                                )).size(12.0).color(egui::Color32::GRAY));
+                                ui.label(egui::RichText::new(err).small().color(ui.visuals().error_fg_color));
                             });
                         }
+                        None => {}
                     }
 
                     ui.add_space(10.0);
                     ui.separator();
 
-                    egui::ScrollArea::vertical().show_rows(
+                    let mut near_end = false;
+                    let mut shown = None;
+                    let mut clicked = None;
+                    let mut menu_action = None;
+                    let mut visible = 0..0;
+                    let query = self.current_query.as_ref();
+                    let mut scroll_area = egui::ScrollArea::vertical();
+                    // A selection moved off screen is first brought among the rows drawn
+                    // (near the bottom when going down), then scrolled to exactly below
+                    let row_height = self.settings.theme.row_height();
+                    let row_step = row_height + ui.spacing().item_spacing.y;
+                    let scroll_to = self.selected.filter(|_| self.scroll_to_selected);
+                    if let Some(row) = scroll_to.filter(|row| !self.visible_rows.contains(row)) {
+                        let top = if row < self.visible_rows.start { row } else { row.saturating_sub(self.visible_rows.len().saturating_sub(2)) };
+                        scroll_area = scroll_area.vertical_scroll_offset(top as f32 * row_step);
+                    }
+                    scroll_area.show_rows(
                         ui,
-                        24.0, // Fixed row height
+                        row_height,
                         self.search_results.len(),
                         |ui, row_range| {
                             // Use manual layout for full control over rows
                             ui.style_mut().spacing.item_spacing.y = 0.0;
+                            near_end = row_range.end + RESULT_PAGE / 2 >= self.search_results.len();
+                            visible = row_range.clone();
 
                             for i in row_range {
                                 if let Some(entry) = self.search_results.get(i) {
                                     let full_path = resolve_path(entry, &self.file_data, &self.drives);
                                     
                                     // 1. Allocate the full row area
-                                    let row_height = 24.0;
                                     let (rect, response) = ui.allocate_exact_size(
                                         egui::vec2(ui.available_width(), row_height), 
                                         egui::Sense::click()
                                     );
 
-                                    // 2. Handle Interaction (Click whole row to open)
+                                    // 2. Handle Interaction (click to select, double-click to open the location)
+                                    // Entries from file lists aren't on this machine, so there is nothing to open
+                                    let is_virtual = self.drives.get(entry.drive_idx as usize).is_some_and(|d| efu::is_virtual_drive(d));
+                                    let offline = self.offline_catalog(entry.drive_idx);
                                     if response.clicked() {
-                                        open_in_explorer(&full_path);
+                                        clicked = Some((i, ui.input(|input| input.modifiers)));
+                                    }
+                                    // Right-click acts on the row, selecting it unless it already is
+                                    if response.secondary_clicked() && !self.selection.contains(&i) {
+                                        clicked = Some((i, egui::Modifiers::NONE));
+                                    }
+                                    let local = offline.is_none() && !is_virtual;
+                                    response.context_menu(|ui| {
+                                        let mut item = |ui: &mut egui::Ui, enabled: bool, label: &str, action: RowAction| {
+                                            if ui.add_enabled(enabled, egui::Button::new(label)).clicked() {
+                                                menu_action = Some((i, action));
+                                                ui.close_menu();
+                                            }
+                                        };
+                                        item(ui, local, "Open", RowAction::Open);
+                                        item(ui, local && !entry.is_dir, "Open with…", RowAction::OpenWith);
+                                        item(ui, local, "Open containing folder", RowAction::ShowInFolder);
+                                        item(ui, local, "Open terminal here", RowAction::Terminal);
+                                        ui.separator();
+                                        item(ui, true, "Copy full path", RowAction::CopyPath);
+                                        item(ui, true, "Copy name", RowAction::CopyName);
+                                        item(ui, true, "Copy parent path", RowAction::CopyParent);
+                                        ui.separator();
+                                        item(ui, true, "Properties", RowAction::Properties);
+                                    });
+                                    if let Some(catalog) = offline {
+                                        let _ = response.on_hover_text(format!(
+                                            "Offline: on {} ({}), not connected",
+                                            if catalog.label.is_empty() { &catalog.name } else { &catalog.label },
+                                            catalog.serial_text()
+                                        ));
+                                    } else if is_virtual {
+                                        let _ = response.on_hover_text("From a file list, not on this computer");
+                                    } else {
+                                        if response.double_clicked() {
+                                            shown = Some(full_path.clone());
+                                        }
+
+                                        // Force pointer cursor when hovering the row
+                                        let _ = response.on_hover_cursor(egui::CursorIcon::PointingHand);
+                                    }
+                                    if scroll_to == Some(i) {
+                                        ui.scroll_to_rect(rect, None);
                                     }
-                                    
-                                    // Force pointer cursor when hovering the row
-                                    let _ = response.on_hover_cursor(egui::CursorIcon::PointingHand);
 
-                                    // 3. Paint Background (Striping + Hover)
+                                    // 3. Paint Background (Selection + Striping + Hover)
                                     // Use rect_contains_pointer to ensure highlight works even if text captures hover
                                     let is_hovered = ui.rect_contains_pointer(rect);
                                     
-                                    let bg_color = if is_hovered {
-                                        Some(egui::Color32::from_rgb(40, 50, 70)) // Distinct Blue-ish hover
+                                    let bg_color = if self.selection.contains(&i) {
+                                        Some(ui.visuals().selection.bg_fill)
+                                    } else if is_hovered {
+                                        Some(ui.visuals().selection.bg_fill.gamma_multiply(0.5)) // Half-way to selected
                                     } else if i % 2 == 1 {
-                                        Some(egui::Color32::from_rgb(45, 45, 50)) // Lighter grey for striping
+                                        Some(ui.visuals().faint_bg_color) // Striping
                                     } else {
                                         None
                                     };
@@ -375,10 +2541,16 @@ impl eframe::App for DeepSearchApp {
                                     ui.allocate_new_ui(egui::UiBuilder::new().max_rect(rect), |ui| {
                                         ui.horizontal_centered(|ui| {
                                             ui.add_space(10.0); // Padding
+                                            let small = egui::TextStyle::Small.resolve(ui.style()).size;
 
-                                            // Icon
+                                            // Icon (dimmed for hidden/system, like Explorer does)
                                             let icon = if entry.is_dir { "📁" } else { "📄" };
-                                            ui.label(icon);
+                                            let dimmed = is_hidden_or_system(entry.attributes);
+                                            ui.label(egui::RichText::new(icon).color(if dimmed {
+                                                ui.visuals().weak_text_color()
+                                            } else {
+                                                ui.visuals().strong_text_color()
+                                            }));
                                             
                                             // Name Column (Fixed Width)
                                             let name_width = 300.0;
@@ -386,13 +2558,36 @@ impl eframe::App for DeepSearchApp {
                                                 egui::vec2(name_width, ui.available_height()),
                                                 egui::Layout::left_to_right(egui::Align::Center),
                                                 |ui| {
-                                                    let name_text = egui::RichText::new(&entry.name).color(egui::Color32::LIGHT_BLUE);
+                                                    let name_color = if dimmed {
+                                                        ui.visuals().hyperlink_color.gamma_multiply(0.6)
+                                                    } else {
+                                                        ui.visuals().hyperlink_color
+                                                    };
+                                                    let spans = query.map(|q| q.name_spans(&entry.name)).unwrap_or_default();
+                                                    let font = egui::TextStyle::Body.resolve(ui.style());
+                                                    let name_text = highlighted(ui.visuals(), &entry.name, &spans, font, name_color);
                                                     ui.add(egui::Label::new(name_text).truncate());
                                                 }
                                             );
 
+                                            // Attribute Column (R, H, S, L, C, O, E)
+                                            ui.allocate_ui_with_layout(
+                                                egui::vec2(50.0, ui.available_height()),
+                                                egui::Layout::left_to_right(egui::Align::Center),
+                                                |ui| {
+                                                    let letters = notable_letters(entry.attributes);
+                                                    ui.label(egui::RichText::new(letters).monospace().size(small).color(ui.visuals().warn_fg_color));
+                                                }
+                                            );
+
+                                            if offline.is_some() {
+                                                ui.label(egui::RichText::new("offline").small().color(ui.visuals().warn_fg_color));
+                                            }
+
                                             // Path Column
-                                            let path_text = egui::RichText::new(&full_path).size(10.0).color(egui::Color32::GRAY);
+                                            let spans = query.map(|q| q.path_spans(&full_path)).unwrap_or_default();
+                                            let font = egui::TextStyle::Small.resolve(ui.style());
+                                            let path_text = highlighted(ui.visuals(), &full_path, &spans, font, ui.visuals().weak_text_color());
                                             ui.add(egui::Label::new(path_text).truncate());
                                         });
                                     });
@@ -400,8 +2595,28 @@ impl eframe::App for DeepSearchApp {
                             }
                         },
                     );
-                        
-                    if self.search_results.is_empty() && !self.search_query.is_empty() {
+                    if scroll_to.is_some_and(|row| visible.contains(&row)) {
+                        self.scroll_to_selected = false;
+                    }
+                    self.visible_rows = visible;
+                    if let Some((row, modifiers)) = clicked {
+                        self.select_row(row, modifiers);
+                        self.results_focused = true;
+                    }
+                    if near_end {
+                        self.load_more();
+                    }
+                    if let Some((row, action)) = menu_action {
+                        self.row_action(ui.ctx(), row, action);
+                    }
+                    if let Some(path) = shown {
+                        match shell::show_in_folder(&path) {
+                            Ok(()) => self.record_history(),
+                            Err(e) => self.action_status = Some(Err(e)),
+                        }
+                    }
+
+                    if self.search_results.is_empty() && self.search_stats.is_some() {
                         ui.vertical_centered(|ui| {
                             ui.add_space(20.0);
                             ui.label("No results found.");
@@ -413,6 +2628,52 @@ impl eframe::App for DeepSearchApp {
     }
 }
 
+// One line of a details grid, in the properties window and the preview pane
+fn detail_row(ui: &mut egui::Ui, label: &str, value: String) {
+    ui.label(egui::RichText::new(label).color(ui.visuals().weak_text_color()));
+    ui.add(egui::Label::new(value).wrap());
+    ui.end_row();
+}
+
+// What the index knows about an entry, as detail rows
+fn entry_details(ui: &mut egui::Ui, entry: &FileEntry) {
+    let unknown = || "unknown".to_string();
+    let kind = if entry.is_dir {
+        "Folder".to_string()
+    } else if entry.ext().is_empty() {
+        "File".to_string()
+    } else {
+        format!("{} file", entry.ext().to_uppercase())
+    };
+    let attributes = attribute_names(entry.attributes).join(", ");
+
+    detail_row(ui, "Type", kind);
+    if !entry.is_dir {
+        detail_row(ui, "Size", format!("{} ({} bytes)", format::human_size(entry.size), entry.size));
+    }
+    detail_row(ui, "Modified", format::display_time(entry.modified).unwrap_or_else(unknown));
+    detail_row(ui, "Created", format::display_time(entry.created).unwrap_or_else(unknown));
+    detail_row(ui, "Attributes", if attributes.is_empty() { "none".to_string() } else { attributes });
+}
+
+// A label with the parts of `text` a query matched picked out
+fn highlighted(visuals: &egui::Visuals, text: &str, spans: &[std::ops::Range<usize>], font: egui::FontId, color: egui::Color32) -> egui::text::LayoutJob {
+    let plain = egui::TextFormat::simple(font.clone(), color);
+    let matched = egui::TextFormat {
+        background: visuals.warn_fg_color.gamma_multiply(0.35),
+        ..egui::TextFormat::simple(font, visuals.strong_text_color())
+    };
+    let mut job = egui::text::LayoutJob::default();
+    let mut pos = 0;
+    for span in spans {
+        job.append(&text[pos..span.start], 0.0, plain.clone());
+        job.append(&text[span.clone()], 0.0, matched.clone());
+        pos = span.end;
+    }
+    job.append(&text[pos..], 0.0, plain);
+    job
+}
+
 fn load_icon() -> egui::IconData {
     let (icon_rgba, icon_width, icon_height) = {
         let icon = image::load_from_memory(include_bytes!("../assets/deep_search.ico"))
@@ -430,9 +2691,21 @@ fn load_icon() -> egui::IconData {
 }
 
 fn main() -> eframe::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("search") => std::process::exit(cli::run_search(&args[1..])),
+        Some("run") => std::process::exit(cli::run_saved(&args[1..])),
+        Some("export-index") => std::process::exit(cli::run_export_index(&args[1..])),
+        Some("--stdio") => std::process::exit(rpc::run_stdio()),
+        Some("--print-default-settings") => std::process::exit(cli::run_print_default_settings()),
+        _ => {}
+    }
+
+    // A broken settings file is reported once the window is up, see DeepSearchApp::new
+    let window = Settings::load_repaired().map(|(s, _)| s.window).unwrap_or_default();
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([800.0, 600.0])
+            .with_inner_size([window.width, window.height])
             .with_icon(load_icon()),
         vsync: true, // Enable VSync to fix flickering
         ..Default::default()
@@ -445,341 +2718,326 @@ fn main() -> eframe::Result<()> {
 }
 
 // --- WORKER LOGIC ---
- // Get a list of fixed drives on the system
-fn get_drives() -> Vec<String> {
-    let mut drives = Vec::new();
-    let bitmask = unsafe { GetLogicalDrives() };
-    
-    for i in 0..26 {
-        if (bitmask & (1 << i)) != 0 {
-            let drive_letter = (b'A' + i) as char;
-            let path = format!("{}:\\\0", drive_letter);
-            
-            let drive_type = unsafe { 
-                GetDriveTypeA(PCSTR(path.as_ptr())) 
-            };
 
-            if drive_type == DRIVE_FIXED || drive_type == DRIVE_REMOVABLE {
-                drives.push(format!("{}:", drive_letter));
-            }
+// What a scan hands back
+struct ScanResult {
+    entries: Vec<FileEntry>,
+    drives: Vec<String>,
+    errors: Vec<String>,
+    statuses: Vec<DriveStatus>, // Every fixed and removable drive, scanned or skipped
+    folders: FolderTree, // Of entries
+    sources: Vec<DriveSource>, // Per drive
+}
+
+// Where a drive of the index came from
+#[derive(Clone, PartialEq)]
+enum DriveSource {
+    Live,
+    Catalog(u32), // An offline catalog, by volume serial
+    FileList(String), // An EFU file list, by its path in the settings
+}
+
+// How a drive fared the last time it was scanned, for the drives window
+#[derive(Clone)]
+struct DriveStatus {
+    drive: String,
+    label: String,
+    filesystem: String,
+    entries: usize,
+    scanned_at: Option<i64>, // Unix seconds
+    state: DriveState,
+}
+
+#[derive(Clone)]
+enum DriveState {
+    Indexed,
+    Skipped, // Left out in the settings
+    Failed(String),
+}
+
+impl DriveStatus {
+    fn new(drive: &str, volume: Option<&Volume>, state: DriveState) -> Self {
+        Self {
+            drive: drive.to_string(),
+            label: volume.map(|v| v.label.clone()).unwrap_or_default(),
+            filesystem: volume.map(|v| v.filesystem.clone()).unwrap_or_default(),
+            entries: 0,
+            scanned_at: None,
+            state,
+        }
+    }
+
+    // The outcome of scanning the drive just now
+    fn scanned(drive: &str, volume: Option<&Volume>, result: &Result<Vec<FileEntry>, String>) -> Self {
+        match result {
+            Ok(entries) => Self {
+                entries: entries.len(),
+                scanned_at: Some(chrono::Utc::now().timestamp()),
+                ..Self::new(drive, volume, DriveState::Indexed)
+            },
+            Err(e) => Self::new(drive, volume, DriveState::Failed(e.clone())),
         }
     }
-    drives
 }
 
-// Scan all fixed drives and return collected FileEntry data
+// Scan the fixed and removable drives not skipped in the settings, load offline catalogs
+// and file lists as virtual drives after them, and return collected FileEntry data
 fn scan_all_drives(
-    tx_progress: crossbeam_channel::Sender<(u64, String)>
-) -> Result<(Vec<FileEntry>, Vec<String>, Vec<String>), String> {
-    let drives = get_drives();
+    tx_progress: crossbeam_channel::Sender<(u64, String)>,
+    file_lists: &[String],
+    scan: &ScanSettings,
+) -> Result<ScanResult, String> {
+    let (skipped, mut drives): (Vec<String>, Vec<String>) = get_drives().into_iter().partition(|d| scan.skips(d));
     let mut all_entries = Vec::new();
     let mut errors = Vec::new();
+    let mut scanned: Vec<Option<Volume>> = Vec::new(); // Of each drive scanned
+    let mut statuses = Vec::new();
     let mut total_count = 0;
 
-    if drives.is_empty() {
+    if drives.is_empty() && skipped.is_empty() && file_lists.is_empty() {
         return Err("No fixed or removable drives found.".to_string());
     }
 
+    // Skipped drives are still mounted, so their catalogs don't stand in for them
+    let mut mounted = Vec::new();
+    for drive in &skipped {
+        let volume = volume_info(drive);
+        statuses.push(DriveStatus::new(drive, volume.as_ref(), DriveState::Skipped));
+        mounted.extend(volume.map(|v| v.serial));
+    }
+
     for (idx, drive) in drives.iter().enumerate() {
         let _ = tx_progress.send((total_count, format!("Scanning {}...", drive)));
         
         // We ignore errors for individual drives so one bad drive doesn't stop everything
         // But if ALL fail, we might want to know.
-        match scan_drive(drive, idx as u8, &tx_progress, &mut total_count) {
-            Ok(entries) => all_entries.extend(entries),
-            Err(e) => errors.push(format!("Failed to scan {}: {}", drive, e)),
+        let result = scan_drive(drive, idx as u8, scan, &tx_progress, &mut total_count);
+        let volume = volume_info(drive);
+        statuses.push(DriveStatus::scanned(drive, volume.as_ref(), &result));
+        match result {
+            Ok(entries) => {
+                all_entries.extend(entries);
+                scanned.push(volume);
+            }
+            Err(e) => {
+                errors.push(format!("Failed to scan {}: {}", drive, e));
+                scanned.push(None);
+            }
         }
     }
-    
-    // Sort by (drive_idx, id) to enable binary search for parent resolution
-    // This is CRITICAL for resolve_path to work correctly across multiple drives
-    all_entries.par_sort_unstable_by(|a, b| {
-        a.drive_idx.cmp(&b.drive_idx).then(a.id.cmp(&b.id))
-    });
+    statuses.sort_by(|a, b| a.drive.cmp(&b.drive));
 
-    Ok((all_entries, drives, errors))
-}
-
-fn scan_drive(
-    drive_letter: &str, 
-    drive_idx: u8,
-    tx: &crossbeam_channel::Sender<(u64, String)>,
-    total_count: &mut u64
-) -> Result<Vec<FileEntry>, String> {
-    let volume_path_str = format!("\\\\.\\{}", drive_letter);
-    let volume_path: Vec<u16> = OsString::from(&volume_path_str).encode_wide().chain(Some(0)).collect();
+    // Catalogs of drives that weren't just scanned stand in for them
+    let catalogs = catalog::load_all().unwrap_or_else(|e| {
+        errors.push(e);
+        Vec::new()
+    });
+    let is_scanned = |serial: u32| scanned.iter().flatten().any(|v| v.serial == serial) || mounted.contains(&serial);
+    let mut virtual_drives: Vec<(PathBuf, String, DriveSource)> = catalogs.iter()
+        .filter(|c| !is_scanned(c.serial))
+        .filter_map(|c| Some((c.list_path()?, c.drive_name(), DriveSource::Catalog(c.serial))))
+        .collect();
+    virtual_drives.extend(file_lists.iter().map(|list| {
+        let path = PathBuf::from(list);
+        let name = efu::virtual_drive_name(&path);
+        (path, name, DriveSource::FileList(list.clone()))
+    }));
+
+    let live_drives = drives.len();
+    let mut sources = vec![DriveSource::Live; live_drives];
+    for (path, base, source) in virtual_drives {
+        let _ = tx_progress.send((total_count, format!("Loading {}...", base)));
+        match load_virtual_drive(&path, &base, &mut all_entries, &mut drives) {
+            Ok(count) => {
+                total_count += count as u64;
+                sources.push(source);
+            }
+            Err(e) => errors.push(e),
+        }
+    }
     
-    // Initial open with WRITE access to create journal if needed
-    let handle_raw = unsafe {
-        CreateFileW(
-            PCWSTR(volume_path.as_ptr()),
-            GENERIC_READ.0 | GENERIC_WRITE.0, 
-            FILE_SHARE_READ | FILE_SHARE_WRITE,
-            None,
-            OPEN_EXISTING,
-            FILE_FLAGS_AND_ATTRIBUTES(0),
-            HANDLE(ptr::null_mut()),
-        )
-    };
-
-    if handle_raw == Ok(INVALID_HANDLE_VALUE) || handle_raw.is_err() {
-        return Err(format!("Access Denied to {}. Run as Administrator.", drive_letter));
-    }
-    let mut handle = SafeHandle(handle_raw.unwrap());
-
-    let mut journal_data = UsnJournalData::default();
-    let mut bytes_returned = 0u32;
-    let success = unsafe {
-        DeviceIoControl(
-            handle.0,
-            FSCTL_QUERY_USN_JOURNAL,
-            None,
-            0,
-            Some(&mut journal_data as *mut _ as *mut c_void),
-            size_of::<UsnJournalData>() as u32,
-            Some(&mut bytes_returned),
-            None,
-        )
-    };
-
-    if success.is_err() {
-        // Try to create the journal if it doesn't exist
-        let mut create_data = CreateUsnJournalData {
-            maximum_size: 0,
-            allocation_delta: 0,
-        };
-        let create_success = unsafe {
-            DeviceIoControl(
-                handle.0,
-                FSCTL_CREATE_USN_JOURNAL,
-                Some(&mut create_data as *mut _ as *mut c_void),
-                size_of::<CreateUsnJournalData>() as u32,
-                None,
-                0,
-                Some(&mut bytes_returned),
-                None,
-            )
-        };
+    let children = link_entries(&mut all_entries);
 
-        if create_success.is_err() {
-             return Err(format!("Failed to query or create USN Journal on {}. Is it NTFS?", drive_letter));
-        }
+    // Lists come with their sizes and dates
+    if scan.read_metadata {
+        fill_metadata(&mut all_entries, &children, &drives[..live_drives], &tx_progress, total_count);
+    }
 
-        // Fix 5: Drop write access - Reopen with GENERIC_READ only
-        drop(handle); // Close current handle
-        
-        let handle_read_raw = unsafe {
-            CreateFileW(
-                PCWSTR(volume_path.as_ptr()),
-                GENERIC_READ.0, // Read only
-                FILE_SHARE_READ | FILE_SHARE_WRITE,
-                None,
-                OPEN_EXISTING,
-                FILE_FLAGS_AND_ATTRIBUTES(0),
-                HANDLE(ptr::null_mut()),
-            )
+    // Keep the catalogs of mounted drives up to date with what they hold now
+    for catalog in &catalogs {
+        let Some(idx) = scanned.iter().position(|v| v.as_ref().is_some_and(|v| v.serial == catalog.serial)) else {
+            continue;
         };
-        
-        if handle_read_raw == Ok(INVALID_HANDLE_VALUE) || handle_read_raw.is_err() {
-            return Err(format!("Failed to reopen {} for reading.", drive_letter));
-        }
-        handle = SafeHandle(handle_read_raw.unwrap());
-
-        // Retry query with new handle
-        let success_retry = unsafe {
-            DeviceIoControl(
-                handle.0,
-                FSCTL_QUERY_USN_JOURNAL,
-                None,
-                0,
-                Some(&mut journal_data as *mut _ as *mut c_void),
-                size_of::<UsnJournalData>() as u32,
-                Some(&mut bytes_returned),
-                None,
-            )
-        };
-        
-        if success_retry.is_err() {
-             return Err(format!("Failed to query USN Journal on {} after creation attempt.", drive_letter));
+        let label = scanned[idx].as_ref().map_or("", |v| v.label.as_str());
+        if let Err(e) = catalog::save(&catalog.name, catalog.serial, label, idx as u8, &all_entries, &drives) {
+            errors.push(e);
         }
     }
 
-    let mut med = MftEnumData {
-        start_file_reference_number: 0,
-        low_usn: 0,
-        high_usn: journal_data.max_usn,
-    };
+    let folders = FolderTree::new(&all_entries);
+    Ok(ScanResult { entries: all_entries, drives, errors, statuses, folders, sources })
+}
 
-    let mut buffer = vec![0u8; 65536]; // 64KB Buffer
-    let mut entries = Vec::with_capacity(100_000); 
-
-    loop {
-        // Fix 8: Cap total entries
-        if entries.len() > 10_000_000 {
-            return Err("Too many files — skipping rest for safety".to_string());
-        }
-
-        let success = unsafe {
-            DeviceIoControl(
-                handle.0,
-                FSCTL_ENUM_USN_DATA,
-                Some(&mut med as *mut _ as *mut c_void),
-                size_of::<MftEnumData>() as u32,
-                Some(buffer.as_mut_ptr() as *mut c_void),
-                buffer.len() as u32,
-                Some(&mut bytes_returned),
-                None,
-            )
-        };
+// Load a file list or catalog as the next drive, named `base` unless that is taken.
+// Returns how many entries it added.
+fn load_virtual_drive(path: &Path, base: &str, entries: &mut Vec<FileEntry>, drives: &mut Vec<String>) -> Result<usize, String> {
+    if drives.len() > u8::MAX as usize {
+        return Err(format!("Too many drives to load {}", path.display()));
+    }
+    let loaded = efu::load_list(path, drives.len() as u8)?;
+    let count = loaded.len();
+    entries.extend(loaded);
+
+    // Two lists with the same file name still need telling apart
+    let mut name = base.to_string();
+    let mut n = 2;
+    while drives.contains(&name) {
+        name = format!("{} {}]", base.trim_end_matches(']'), n);
+        n += 1;
+    }
+    drives.push(name);
+    Ok(count)
+}
 
-        if success.is_err() { break; }
-
-        let mut offset = 8; 
-        while offset < bytes_returned as usize {
-            // Fix 1: Safe parsing
-            if offset + USN_RECORD_HEADER_SIZE > bytes_returned as usize { break; }
-            
-            let p_record = unsafe { ptr::read_unaligned(buffer.as_ptr().add(offset) as *const UsnRecordHeader) };
-            let rec_len = p_record.record_length as usize;
-            
-            if rec_len < USN_RECORD_HEADER_SIZE || rec_len == 0 || offset + rec_len > bytes_returned as usize { 
-                // If record length is invalid, we can't trust the rest of the buffer
-                break; 
-            }
-
-            let fname_len = p_record.file_name_length as usize;
-            let fname_off = p_record.file_name_offset as usize;
-
-            if fname_len > 0 {
-                // Validate filename offset and length
-                // Use constant 60 because size_of struct might include padding (64 bytes)
-                if fname_len % 2 != 0 || fname_off < USN_RECORD_HEADER_SIZE || fname_off + fname_len > rec_len {
-                    offset += rec_len;
-                    continue;
-                }
-
-                let name_slice = unsafe {
-                    std::slice::from_raw_parts(
-                        buffer.as_ptr().add(offset + fname_off) as *const u16,
-                        fname_len / 2,
-                    )
-                };
-                
-                let name = String::from_utf16_lossy(name_slice);
-                
-                // Fix 2: Block malicious filenames
-                if name.contains(':') || name.contains("::") || name.ends_with(".lnk") || (name.contains('{') && name.contains('}')) || name.contains("\\\\?\\") {
-                    offset += rec_len;
-                    continue;
-                }
-
-                let is_dir = (p_record.file_attributes & FILE_ATTRIBUTE_DIRECTORY) != 0;
-
-                entries.push(FileEntry {
-                    id: p_record.file_reference_number,
-                    parent_id: p_record.parent_file_reference_number,
-                    name,
-                    is_dir,
-                    drive_idx,
-                });
+// The index with its file lists changed to `lists`. The drives of lists no longer wanted
+// are taken out and the drives after them move down into their place, new lists are
+// loaded as drives at the end, and everything else is kept as it is.
+fn relist(base: Arc<Vec<FileEntry>>, drives: &[String], sources: &[DriveSource], lists: &[String]) -> Relisted {
+    let kept: Vec<usize> = (0..drives.len())
+        .filter(|&i| !matches!(sources.get(i), Some(DriveSource::FileList(path)) if !lists.contains(path)))
+        .collect();
+    let mut new_idx = vec![None; drives.len()];
+    for (new, &old) in kept.iter().enumerate() {
+        new_idx[old] = Some(new as u8);
+    }
 
-                *total_count += 1;
-            }
-            offset += rec_len;
-        }
+    let mut entries: Vec<FileEntry> = base.iter()
+        .filter_map(|e| {
+            let drive_idx = new_idx.get(e.drive_idx as usize).copied().flatten()?;
+            Some(FileEntry { drive_idx, ..e.clone() })
+        })
+        .collect();
+    let mut new_drives: Vec<String> = kept.iter().map(|&i| drives[i].clone()).collect();
+    let mut new_sources: Vec<DriveSource> = kept.iter().map(|&i| sources.get(i).cloned().unwrap_or(DriveSource::Live)).collect();
 
-        // Report progress every ~2k files
-        if *total_count % 2_000 == 0 {
-            let _ = tx.send((*total_count, format!("Scanning {}...", drive_letter)));
+    let mut errors = Vec::new();
+    for list in lists {
+        let source = DriveSource::FileList(list.clone());
+        if new_sources.contains(&source) {
+            continue;
+        }
+        let path = PathBuf::from(list);
+        match load_virtual_drive(&path, &efu::virtual_drive_name(&path), &mut entries, &mut new_drives) {
+            Ok(_) => new_sources.push(source),
+            Err(e) => errors.push(e),
         }
-
-        if bytes_returned < 8 { break; }
-        med.start_file_reference_number = unsafe { ptr::read_unaligned(buffer.as_ptr() as *const u64) };
     }
 
-    // Handle is closed automatically by SafeHandle
+    link_entries(&mut entries);
+    let folders = FolderTree::new(&entries);
+    Relisted { base, entries, folders, drives: new_drives, sources: new_sources, errors }
+}
+
+// Sort by (drive_idx, id) to enable binary search for parent resolution, and number the
+// subtrees. Returns the children of every folder.
+fn link_entries(entries: &mut [FileEntry]) -> HashMap<(u8, u64), Vec<usize>> {
+    // This is CRITICAL for resolve_path to work correctly across multiple drives
+    entries.par_sort_unstable_by(|a, b| {
+        a.drive_idx.cmp(&b.drive_idx).then(a.id.cmp(&b.id))
+    });
 
-    Ok(entries)
+    let mut children: HashMap<(u8, u64), Vec<usize>> = HashMap::new();
+    for (i, e) in entries.iter().enumerate() {
+        children.entry((e.drive_idx, e.parent_id)).or_default().push(i);
+    }
+    number_subtrees(entries, &children);
+    children
 }
 
-fn resolve_path(entry: &FileEntry, data: &[FileEntry], drives: &[String]) -> String {
-    let mut parts = Vec::new();
-    let mut current_id = entry.id;
-    let drive_idx = entry.drive_idx;
-    let mut safety = 0;
+// Scan one drive of the index again. Returns the index with the drive's new entries in
+// place of its old ones, or None if the drive couldn't be read, and the drive's status.
+fn rescan_drive(data: &[FileEntry], drives: &[String], drive_idx: u8, scan: &ScanSettings) -> (Option<Vec<FileEntry>>, DriveStatus) {
+    let drive = &drives[drive_idx as usize];
+    // Progress is only shown for full scans
+    let (tx_progress, _rx_progress) = crossbeam_channel::unbounded();
+    let result = scan_drive(drive, drive_idx, scan, &tx_progress, &mut 0);
+    let volume = volume_info(drive);
+    let status = DriveStatus::scanned(drive, volume.as_ref(), &result);
+    let Ok(fresh) = result else {
+        return (None, status);
+    };
 
-    loop {
-        // Binary search for (drive_idx, current_id)
-        // Since data is sorted by drive_idx then id, we can find the exact entry
-        let result = data.binary_search_by(|e| {
-            e.drive_idx.cmp(&drive_idx).then(e.id.cmp(&current_id))
-        });
+    let mut entries: Vec<FileEntry> = data.iter().filter(|e| e.drive_idx != drive_idx).cloned().chain(fresh).collect();
+    let mut children = link_entries(&mut entries);
+    children.retain(|&(idx, _), _| idx == drive_idx);
+    if scan.read_metadata {
+        fill_metadata(&mut entries, &children, drives, &tx_progress, 0);
+    }
 
-        if let Ok(idx) = result {
-            let e = &data[idx];
+    // Its catalog, if it has one, follows along as in a full scan
+    let catalogs = catalog::load_all().unwrap_or_default();
+    if let Some((volume, catalog)) = volume.as_ref().and_then(|v| Some((v, catalogs.iter().find(|c| c.serial == v.serial)?))) {
+        let _ = catalog::save(&catalog.name, catalog.serial, &volume.label, drive_idx, &entries, drives);
+    }
+    (Some(entries), status)
+}
 
-            // Stop at root (parent points to self)
-            if e.parent_id == current_id {
-                break;
+// The USN records carry no size or timestamps, so fill them in by listing every directory once.
+// On Windows read_dir gets size and times along with each name, so this costs one
+// FindFirstFile/FindNextFile sweep per directory instead of a metadata call per file.
+// Reports how many directories are done every so often, as that can take minutes.
+fn fill_metadata(
+    entries: &mut [FileEntry],
+    children: &HashMap<(u8, u64), Vec<usize>>,
+    drives: &[String],
+    tx: &crossbeam_channel::Sender<(u64, String)>,
+    total_count: u64
+) {
+    let data: &[FileEntry] = entries;
+    let dirs = children.keys().filter(|(drive_idx, _)| (*drive_idx as usize) < drives.len()).count();
+    let done = AtomicUsize::new(0);
+    let _ = tx.send((total_count, format!("Reading sizes and dates of {} folders...", dirs)));
+
+    let updates: Vec<(usize, u64, i64, i64)> = children.par_iter()
+        .filter(|((drive_idx, _), _)| (*drive_idx as usize) < drives.len())
+        .flat_map_iter(|(&(drive_idx, dir_id), kids)| {
+            let finished = done.fetch_add(1, Ordering::Relaxed) + 1;
+            if finished.is_multiple_of(1_000) {
+                let _ = tx.send((total_count, format!("Reading sizes and dates: {} of {} folders...", finished, dirs)));
             }
 
-            if e.name != "." && e.name != ".." {
-                parts.push(e.name.clone());
+            // A parent we never saw is the volume root
+            let dir_path = match data.binary_search_by(|e| e.drive_idx.cmp(&drive_idx).then(e.id.cmp(&dir_id))) {
+                Ok(idx) => resolve_path(&data[idx], data, drives),
+                Err(_) => format!("{}\\", drives.get(drive_idx as usize).map_or("?", |d| d.as_str())),
+            };
+
+            let by_name: HashMap<&str, usize> = kids.iter().map(|&i| (data[i].name.as_str(), i)).collect();
+            let mut found = Vec::new();
+            if let Ok(listing) = std::fs::read_dir(&dir_path) {
+                for item in listing.flatten() {
+                    let name = item.file_name();
+                    let (Some(&i), Ok(meta)) = (by_name.get(name.to_string_lossy().as_ref()), item.metadata()) else {
+                        continue;
+                    };
+                    let size = if meta.is_dir() { 0 } else { meta.len() };
+                    found.push((i, size, unix_seconds(meta.modified()), unix_seconds(meta.created())));
+                }
             }
-            current_id = e.parent_id;
-            
-            safety += 1;
-            if safety > 200 { break; } // Cycle/Depth protection
-        } else {
-            // If we can't find the parent, we assume we've reached the root.
-            break;
-        }
-    }
-    parts.reverse();
-    let path = parts.join("\\");
-    
-    // Prepend the correct drive letter
-    if let Some(drive) = drives.get(drive_idx as usize) {
-        format!("{}\\{}", drive, path)
-    } else {
-        format!("?\\{}", path) // Fallback
+            found
+        })
+        .collect();
+
+    for (i, size, modified, created) in updates {
+        let entry = &mut entries[i];
+        entry.size = size;
+        entry.modified = modified;
+        entry.created = created;
     }
 }
 
-
-// Open the given path in Windows Explorer, selecting the file if possible
-fn open_in_explorer(path: &str) {
-    println!("Attempting to open: {}", path);
-    
-    // Fix 4: Canonicalize + validate path
-    // We use std::path::Path to check existence, but we pass the original string to explorer
-    // because canonicalize() adds \\?\ prefix which explorer /select sometimes dislikes
-    if !std::path::Path::new(path).exists() {
-        eprintln!("File does not exist or invalid path: {}", path);
-        return;
-    }
-
-    // Fix 3: Use ShellExecuteW with /select
-    // This is safer than Command::spawn because it avoids cmd.exe parsing issues
-    // We convert the path to absolute if it isn't already, but keep it simple
-    let abs_path = std::fs::canonicalize(path).unwrap_or_else(|_| std::path::PathBuf::from(path));
-    // Remove \\?\ prefix if present, as it can confuse some versions of Explorer with /select
-    let path_str = abs_path.to_string_lossy().replace("\\\\?\\", "");
-    
-    let params = format!("/select,\"{}\"", path_str);
-    
-    let op = "open\0".encode_utf16().collect::<Vec<u16>>();
-    let file = "explorer.exe\0".encode_utf16().collect::<Vec<u16>>();
-    let params_wide: Vec<u16> = OsString::from(&params).encode_wide().chain(Some(0)).collect();
-
-    unsafe {
-        ShellExecuteW(
-            None,
-            PCWSTR(op.as_ptr()),
-            PCWSTR(file.as_ptr()),
-            PCWSTR(params_wide.as_ptr()),
-            None,
-            SW_SHOW
-        );
-    }
-}
\ No newline at end of file
+fn unix_seconds(time: std::io::Result<SystemTime>) -> i64 {
+    time.ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs() as i64)
+}
@@ -0,0 +1,363 @@
+// Reading a drive's file names straight from the NTFS master file table via its USN
+// journal, which takes seconds where walking the folders takes minutes. Only Windows
+// has it; elsewhere there are no drives to scan and the index is built from catalogs
+// and file lists alone.
+
+pub use platform::{get_drives, scan_drive, volume_info};
+
+// A mounted volume as Windows describes it
+pub struct Volume {
+    pub serial: u32, // For matching it with its catalog
+    pub label: String,
+    pub filesystem: String,
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::Volume;
+    use crate::settings::ScanSettings;
+    use deep_search::attributes::FILE_ATTRIBUTE_DIRECTORY;
+    use deep_search::FileEntry;
+    use std::ffi::{c_void, OsString};
+    use std::mem::size_of;
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr;
+    use windows::core::{PCSTR, PCWSTR};
+    use windows::Win32::Foundation::{GENERIC_READ, GENERIC_WRITE, HANDLE, INVALID_HANDLE_VALUE, CloseHandle};
+    use windows::Win32::Storage::FileSystem::{
+        CreateFileW, GetLogicalDrives, GetDriveTypeA, GetVolumeInformationW, FILE_FLAGS_AND_ATTRIBUTES, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+    };
+    use windows::Win32::System::IO::DeviceIoControl;
+    use windows::Win32::System::Ioctl::{FSCTL_ENUM_USN_DATA, FSCTL_QUERY_USN_JOURNAL, FSCTL_CREATE_USN_JOURNAL};
+
+    const DRIVE_REMOVABLE: u32 = 2;
+    const DRIVE_FIXED: u32 = 3;
+
+
+    struct SafeHandle(HANDLE);
+    impl Drop for SafeHandle {
+        fn drop(&mut self) {
+            unsafe { let _ = CloseHandle(self.0); }
+        }
+    }
+
+    // --- RAW NTFS STRUCTURES --- for storing the values read from teh MFT table
+
+    //Similar to the USN_JOURNAL_DATA_V0 structure in C
+    #[repr(C)] // Tells rust compiler to use C-style memory layout
+    #[derive(Debug, Default)] // Can be printed with {:?} and has a default constructor
+    struct UsnJournalData {
+        usn_journal_id: u64,
+        first_usn: i64,
+        next_usn: i64,
+        lowest_valid_usn: i64,
+        max_usn: i64,
+        maximum_size: u64,
+        allocation_delta: u64,
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Default)]
+    struct CreateUsnJournalData {
+        maximum_size: u64,
+        allocation_delta: u64,
+    }
+
+    // Similar to the MFT_ENUM_DATA structure in C
+    #[repr(C)]
+    struct MftEnumData {
+        start_file_reference_number: u64,
+        low_usn: i64,
+        high_usn: i64,
+    }
+
+    // Similar to the USN_RECORD structure in C
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy)]
+    struct UsnRecordHeader {
+        record_length: u32,
+        major_version: u16,
+        minor_version: u16,
+        file_reference_number: u64,
+        parent_file_reference_number: u64,
+        usn: i64,
+        timestamp: i64,
+        reason: u32,
+        source_info: u32,
+        security_id: u32,
+        file_attributes: u32,
+        file_name_length: u16,
+        file_name_offset: u16,
+    }
+
+    const USN_RECORD_HEADER_SIZE: usize = 60;
+
+
+    // Get a list of fixed drives on the system
+    pub fn get_drives() -> Vec<String> {
+        let mut drives = Vec::new();
+        let bitmask = unsafe { GetLogicalDrives() };
+
+        for i in 0..26 {
+            if (bitmask & (1 << i)) != 0 {
+                let drive_letter = (b'A' + i) as char;
+                let path = format!("{}:\\\0", drive_letter);
+
+                let drive_type = unsafe { 
+                    GetDriveTypeA(PCSTR(path.as_ptr())) 
+                };
+
+                if drive_type == DRIVE_FIXED || drive_type == DRIVE_REMOVABLE {
+                    drives.push(format!("{}:", drive_letter));
+                }
+            }
+        }
+        drives
+    }
+
+    pub fn volume_info(drive: &str) -> Option<Volume> {
+        let root: Vec<u16> = OsString::from(format!("{}\\", drive)).encode_wide().chain(Some(0)).collect();
+        let mut label = [0u16; 261];
+        let mut filesystem = [0u16; 261];
+        let mut serial = 0u32;
+        unsafe {
+            GetVolumeInformationW(PCWSTR(root.as_ptr()), Some(&mut label), Some(&mut serial), None, None, Some(&mut filesystem)).ok()?;
+        }
+        let text = |buffer: &[u16]| {
+            let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+            String::from_utf16_lossy(&buffer[..len])
+        };
+        Some(Volume { serial, label: text(&label), filesystem: text(&filesystem) })
+    }
+
+
+    pub fn scan_drive(
+        drive_letter: &str, 
+        drive_idx: u8,
+        scan: &ScanSettings,
+        tx: &crossbeam_channel::Sender<(u64, String)>,
+        total_count: &mut u64
+    ) -> Result<Vec<FileEntry>, String> {
+        let volume_path_str = format!("\\\\.\\{}", drive_letter);
+        let volume_path: Vec<u16> = OsString::from(&volume_path_str).encode_wide().chain(Some(0)).collect();
+
+        // Initial open with WRITE access to create journal if needed
+        let handle_raw = unsafe {
+            CreateFileW(
+                PCWSTR(volume_path.as_ptr()),
+                GENERIC_READ.0 | GENERIC_WRITE.0, 
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                None,
+                OPEN_EXISTING,
+                FILE_FLAGS_AND_ATTRIBUTES(0),
+                HANDLE(ptr::null_mut()),
+            )
+        };
+
+        if handle_raw == Ok(INVALID_HANDLE_VALUE) || handle_raw.is_err() {
+            return Err(format!("Access Denied to {}. Run as Administrator.", drive_letter));
+        }
+        let mut handle = SafeHandle(handle_raw.unwrap());
+
+        let mut journal_data = UsnJournalData::default();
+        let mut bytes_returned = 0u32;
+        let success = unsafe {
+            DeviceIoControl(
+                handle.0,
+                FSCTL_QUERY_USN_JOURNAL,
+                None,
+                0,
+                Some(&mut journal_data as *mut _ as *mut c_void),
+                size_of::<UsnJournalData>() as u32,
+                Some(&mut bytes_returned),
+                None,
+            )
+        };
+
+        if success.is_err() {
+            // Try to create the journal if it doesn't exist
+            let mut create_data = CreateUsnJournalData {
+                maximum_size: 0,
+                allocation_delta: 0,
+            };
+            let create_success = unsafe {
+                DeviceIoControl(
+                    handle.0,
+                    FSCTL_CREATE_USN_JOURNAL,
+                    Some(&mut create_data as *mut _ as *mut c_void),
+                    size_of::<CreateUsnJournalData>() as u32,
+                    None,
+                    0,
+                    Some(&mut bytes_returned),
+                    None,
+                )
+            };
+
+            if create_success.is_err() {
+                 return Err(format!("Failed to query or create USN Journal on {}. Is it NTFS?", drive_letter));
+            }
+
+            // Fix 5: Drop write access - Reopen with GENERIC_READ only
+            drop(handle); // Close current handle
+
+            let handle_read_raw = unsafe {
+                CreateFileW(
+                    PCWSTR(volume_path.as_ptr()),
+                    GENERIC_READ.0, // Read only
+                    FILE_SHARE_READ | FILE_SHARE_WRITE,
+                    None,
+                    OPEN_EXISTING,
+                    FILE_FLAGS_AND_ATTRIBUTES(0),
+                    HANDLE(ptr::null_mut()),
+                )
+            };
+
+            if handle_read_raw == Ok(INVALID_HANDLE_VALUE) || handle_read_raw.is_err() {
+                return Err(format!("Failed to reopen {} for reading.", drive_letter));
+            }
+            handle = SafeHandle(handle_read_raw.unwrap());
+
+            // Retry query with new handle
+            let success_retry = unsafe {
+                DeviceIoControl(
+                    handle.0,
+                    FSCTL_QUERY_USN_JOURNAL,
+                    None,
+                    0,
+                    Some(&mut journal_data as *mut _ as *mut c_void),
+                    size_of::<UsnJournalData>() as u32,
+                    Some(&mut bytes_returned),
+                    None,
+                )
+            };
+
+            if success_retry.is_err() {
+                 return Err(format!("Failed to query USN Journal on {} after creation attempt.", drive_letter));
+            }
+        }
+
+        let mut med = MftEnumData {
+            start_file_reference_number: 0,
+            low_usn: 0,
+            high_usn: journal_data.max_usn,
+        };
+
+        let mut buffer = vec![0u8; scan.buffer_kb as usize * 1024];
+        let mut entries = Vec::with_capacity(100_000); 
+
+        loop {
+            // Fix 8: Cap total entries
+            if entries.len() as u64 > scan.max_entries {
+                return Err("Too many files — skipping rest for safety".to_string());
+            }
+
+            let success = unsafe {
+                DeviceIoControl(
+                    handle.0,
+                    FSCTL_ENUM_USN_DATA,
+                    Some(&mut med as *mut _ as *mut c_void),
+                    size_of::<MftEnumData>() as u32,
+                    Some(buffer.as_mut_ptr() as *mut c_void),
+                    buffer.len() as u32,
+                    Some(&mut bytes_returned),
+                    None,
+                )
+            };
+
+            if success.is_err() { break; }
+
+            let mut offset = 8; 
+            while offset < bytes_returned as usize {
+                // Fix 1: Safe parsing
+                if offset + USN_RECORD_HEADER_SIZE > bytes_returned as usize { break; }
+
+                let p_record = unsafe { ptr::read_unaligned(buffer.as_ptr().add(offset) as *const UsnRecordHeader) };
+                let rec_len = p_record.record_length as usize;
+
+                if rec_len < USN_RECORD_HEADER_SIZE || rec_len == 0 || offset + rec_len > bytes_returned as usize { 
+                    // If record length is invalid, we can't trust the rest of the buffer
+                    break; 
+                }
+
+                let fname_len = p_record.file_name_length as usize;
+                let fname_off = p_record.file_name_offset as usize;
+
+                if fname_len > 0 {
+                    // Validate filename offset and length
+                    // Use constant 60 because size_of struct might include padding (64 bytes)
+                    if fname_len % 2 != 0 || fname_off < USN_RECORD_HEADER_SIZE || fname_off + fname_len > rec_len {
+                        offset += rec_len;
+                        continue;
+                    }
+
+                    let name_slice = unsafe {
+                        std::slice::from_raw_parts(
+                            buffer.as_ptr().add(offset + fname_off) as *const u16,
+                            fname_len / 2,
+                        )
+                    };
+
+                    // Every name is kept, however odd: paths are checked where they are
+                    // handed to other programs instead, see actions::checked_path
+                    let name = String::from_utf16_lossy(name_slice);
+
+                    let is_dir = (p_record.file_attributes & FILE_ATTRIBUTE_DIRECTORY) != 0;
+
+                    entries.push(FileEntry {
+                        id: p_record.file_reference_number,
+                        parent_id: p_record.parent_file_reference_number,
+                        name,
+                        is_dir,
+                        drive_idx,
+                        size: 0, // Filled in by fill_metadata once the whole index is sorted
+                        modified: 0,
+                        created: 0,
+                        attributes: p_record.file_attributes,
+                        order: 0, // Set by number_subtrees
+                        subtree_end: 0,
+                    });
+
+                    *total_count += 1;
+                }
+                offset += rec_len;
+            }
+
+            // Report progress every ~2k files
+            if *total_count % 2_000 == 0 {
+                let _ = tx.send((*total_count, format!("Scanning {}...", drive_letter)));
+            }
+
+            if bytes_returned < 8 { break; }
+            med.start_file_reference_number = unsafe { ptr::read_unaligned(buffer.as_ptr() as *const u64) };
+        }
+
+        // Handle is closed automatically by SafeHandle
+
+        Ok(entries)
+    }
+}
+
+#[cfg(not(windows))]
+mod platform {
+    use super::Volume;
+    use crate::settings::ScanSettings;
+    use deep_search::FileEntry;
+
+    pub fn get_drives() -> Vec<String> {
+        Vec::new()
+    }
+
+    pub fn volume_info(_drive: &str) -> Option<Volume> {
+        None
+    }
+
+    pub fn scan_drive(
+        drive_letter: &str,
+        _drive_idx: u8,
+        _scan: &ScanSettings,
+        _tx: &crossbeam_channel::Sender<(u64, String)>,
+        _total_count: &mut u64
+    ) -> Result<Vec<FileEntry>, String> {
+        Err(format!("Can't scan {}: reading the MFT needs Windows", drive_letter))
+    }
+}
@@ -0,0 +1,84 @@
+// What the preview pane shows of a file's contents: the start of text files, decoded
+// from whatever encoding they look like, and a thumbnail of images. Loading happens on
+// a worker thread that only ever works on the latest request, so holding an arrow key
+// down the results doesn't queue up a read for every row passed.
+
+use crossbeam_channel::{Receiver, Sender};
+use deep_search::encoding::decode_text;
+use deep_search::format;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::thread;
+
+// How much of a text file is shown
+pub const TEXT_BYTES: usize = 16 * 1024;
+// Thumbnails fit in a square this size
+const THUMBNAIL_SIZE: u32 = 320;
+// Larger images aren't decoded just for a thumbnail
+const IMAGE_BYTES: u64 = 64 * 1024 * 1024;
+
+pub enum Content {
+    Text { text: String, encoding: &'static str, truncated: bool },
+    Image { width: u32, height: u32, thumbnail: egui::ColorImage },
+    Binary, // Neither text nor an image that can be decoded
+}
+
+// Starts the worker. Paths go in, (path, content) comes out
+pub fn start(tx: Sender<(String, Result<Content, String>)>) -> Sender<String> {
+    let (tx_request, rx_request) = crossbeam_channel::unbounded();
+    thread::spawn(move || {
+        while let Some(path) = latest(&rx_request) {
+            let content = load(&path);
+            if tx.send((path, content)).is_err() {
+                break;
+            }
+        }
+    });
+    tx_request
+}
+
+// Waits for a request and skips to the newest one queued
+fn latest(rx: &Receiver<String>) -> Option<String> {
+    let mut path = rx.recv().ok()?;
+    while let Ok(newer) = rx.try_recv() {
+        path = newer;
+    }
+    Some(path)
+}
+
+pub fn load(path: &str) -> Result<Content, String> {
+    let path = Path::new(path);
+    if image::ImageFormat::from_path(path).is_ok_and(|f| f.reading_enabled()) {
+        return load_image(path);
+    }
+
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut bytes = Vec::new();
+    file.take(TEXT_BYTES as u64 + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let truncated = bytes.len() > TEXT_BYTES;
+    bytes.truncate(TEXT_BYTES);
+
+    Ok(match decode_text(&bytes) {
+        Some((text, encoding)) => Content::Text { text, encoding, truncated },
+        None => Content::Binary,
+    })
+}
+
+fn load_image(path: &Path) -> Result<Content, String> {
+    let size = std::fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?.len();
+    if size > IMAGE_BYTES {
+        return Err(format!("Too large to preview ({})", format::human_size(size)));
+    }
+
+    let image = image::open(path).map_err(|e| format!("Failed to decode image: {}", e))?;
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgba8();
+    let thumbnail_size = [thumbnail.width() as usize, thumbnail.height() as usize];
+    Ok(Content::Image {
+        width: image.width(),
+        height: image.height(),
+        thumbnail: egui::ColorImage::from_rgba_unmultiplied(thumbnail_size, thumbnail.as_raw()),
+    })
+}
@@ -0,0 +1,567 @@
+// Query parsing and matching against the in-memory index
+//
+// A query is a list of whitespace-separated tokens. Tokens of the form `key:value`
+// with a known key become filters, everything else is the name text, which is
+// matched as a case-insensitive prefix of the file name (or, in regex mode, as a
+// case-insensitive regular expression anywhere in it).
+//
+//   ext:jpg;png        extension is one of the listed ones
+//   type:images        extension belongs to the named category
+//   size:>500mb        size comparison (>, >=, <, <=, or exact), units b/kb/mb/gb/tb
+//   size:1kb..10kb     inclusive size range, either side may be left open
+//   dm:today           modified date: today, yesterday, thisweek, lastweek, thismonth,
+//   dc:2025-01..2025-03  lastmonth, thisyear, lastyear, lastNdays, YYYY, YYYY-MM or
+//                      YYYY-MM-DD, with the same comparisons and ranges as size:
+//   attrib:hs          has all the given attributes (letters or names, see attributes.rs)
+//   !attrib:h          any filter can be negated with a leading '!'
+//   parent:D:\repos    anywhere under the folder (infolder: is the same thing)
+//
+// Everything is evaluated against the index, nothing here touches the filesystem.
+// Besides matching, a query can say where in a name or path it matched, for
+// highlighting (name_spans, path_spans).
+
+use crate::attributes::{parse_attributes, FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_SYSTEM};
+use crate::exclude::ExclusionSet;
+use crate::filetype::{find_category, is_one_of, FileCategory};
+use crate::{find_folder, FileEntry, FolderTree};
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone};
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::sync::Arc;
+
+// How the name text of a query is matched
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    #[default]
+    Prefix,
+    Regex,
+}
+
+#[derive(Clone, Debug)]
+pub enum Filter {
+    // Lowercase extensions without the dot
+    Extension(Vec<String>),
+    // Bytes. Directories never match
+    Size(Bounds),
+    // Unix seconds. Entries with an unknown time never match
+    Modified(Bounds),
+    Created(Bounds),
+    // Entry has every one of these attribute bits
+    Attributes(u32),
+    // Anywhere below a folder, excluding the folder itself
+    InFolder(Scope),
+    // Hit by an exclusion rule (only ever added by the app, never typed)
+    Excluded(Arc<ExclusionSet>),
+    Not(Box<Filter>),
+}
+
+#[derive(Clone, Debug)]
+pub enum Scope {
+    // Path as typed, turned into one of the others by Query::resolve
+    Unresolved(String),
+    Drive(u8),
+    // Open interval of FileEntry::order values, see number_subtrees
+    Subtree { start: u32, end: u32 },
+}
+
+impl Filter {
+    fn matches(&self, entry: &FileEntry) -> bool {
+        match self {
+            Filter::Extension(exts) => is_one_of(entry.ext(), exts),
+            Filter::Size(bounds) => !entry.is_dir && bounds.contains(entry.size as i64),
+            Filter::Modified(bounds) => entry.modified != 0 && bounds.contains(entry.modified),
+            Filter::Created(bounds) => entry.created != 0 && bounds.contains(entry.created),
+            Filter::Attributes(mask) => entry.attributes & mask == *mask,
+            Filter::InFolder(scope) => match *scope {
+                Scope::Unresolved(_) => false,
+                Scope::Drive(drive_idx) => entry.drive_idx == drive_idx,
+                Scope::Subtree { start, end } => entry.order > start && entry.order < end,
+            },
+            Filter::Excluded(set) => set.contains(entry),
+            Filter::Not(inner) => !inner.matches(entry),
+        }
+    }
+
+    fn is_attribute(&self) -> bool {
+        match self {
+            Filter::Attributes(_) => true,
+            Filter::Not(inner) => inner.is_attribute(),
+            _ => false,
+        }
+    }
+}
+
+// Half-open interval: min is inclusive, max is exclusive, None is unbounded
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Bounds {
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+}
+
+impl Bounds {
+    fn contains(&self, value: i64) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value < max)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    pub text: String, // Lowercased name prefix
+    pub regex: Option<Regex>, // Set in regex mode, replaces the prefix match
+    pub filters: Vec<Filter>,
+    pub folders: Vec<String>, // Folders results must be under, as given, see path_spans
+}
+
+impl Query {
+    pub fn parse(input: &str, mode: MatchMode, categories: &[FileCategory]) -> Result<Query, String> {
+        let mut words = Vec::new();
+        let mut filters = Vec::new();
+        let mut folders = Vec::new();
+
+        for token in tokenize(input) {
+            let (negated, body) = match token.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, token.as_str()),
+            };
+            let Some((key, value)) = body.split_once(':').filter(|(key, _)| is_filter_key(key)) else {
+                words.push(token);
+                continue;
+            };
+
+            // An empty value is what you get halfway through typing a filter, so it is a no-op
+            if value.is_empty() {
+                continue;
+            }
+
+            let filter = parse_filter(&key.to_lowercase(), value, categories)?;
+            if let (false, Filter::InFolder(Scope::Unresolved(path))) = (negated, &filter) {
+                folders.push(path.clone());
+            }
+            filters.push(if negated { Filter::Not(Box::new(filter)) } else { filter });
+        }
+
+        let text = words.join(" ");
+        let regex = match mode {
+            MatchMode::Regex if !text.is_empty() => Some(
+                RegexBuilder::new(&text)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| format!("Invalid regex: {}", e))?,
+            ),
+            _ => None,
+        };
+
+        Ok(Query {
+            text: text.to_lowercase(),
+            regex,
+            filters,
+            folders,
+        })
+    }
+
+    // Only results under the folder, like parent: in the query text
+    pub fn add_folder(&mut self, path: &str) {
+        self.filters.push(Filter::InFolder(Scope::Unresolved(path.to_string())));
+        self.folders.push(path.to_string());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.filters.is_empty()
+    }
+
+    // Whether the query says anything about attributes itself, in which case
+    // defaults like "hide hidden/system files" shouldn't second-guess it
+    pub fn mentions_attributes(&self) -> bool {
+        self.filters.iter().any(Filter::is_attribute)
+    }
+
+    // Leave out hidden and system entries, unless the query filters on attributes itself
+    pub fn hide_hidden(&mut self) {
+        if !self.mentions_attributes() {
+            self.filters.push(Filter::Not(Box::new(Filter::Attributes(FILE_ATTRIBUTE_HIDDEN))));
+            self.filters.push(Filter::Not(Box::new(Filter::Attributes(FILE_ATTRIBUTE_SYSTEM))));
+        }
+    }
+
+    // Look up folder scopes in the index. Done once per search, so matching itself
+    // is a plain range check on each entry. Every scope that exists is resolved even
+    // when another one doesn't; those stay unresolved and match nothing.
+    pub fn resolve(&mut self, data: &[FileEntry], drives: &[String], folders: &FolderTree) -> Result<(), String> {
+        fn resolve_filter(filter: &mut Filter, data: &[FileEntry], drives: &[String], folders: &FolderTree, missing: &mut Vec<String>) {
+            match filter {
+                Filter::InFolder(scope) => {
+                    if let Scope::Unresolved(path) = scope {
+                        match find_folder(path, data, drives, folders) {
+                            Some(found) => *scope = found,
+                            None => missing.push(path.clone()),
+                        }
+                    }
+                }
+                Filter::Not(inner) => resolve_filter(inner, data, drives, folders, missing),
+                _ => {}
+            }
+        }
+
+        let mut missing = Vec::new();
+        for filter in &mut self.filters {
+            resolve_filter(filter, data, drives, folders, &mut missing);
+        }
+        match missing.first() {
+            Some(path) => Err(format!("Folder not found: {}", path)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn matches(&self, entry: &FileEntry) -> bool {
+        if !self.filters.iter().all(|f| f.matches(entry)) {
+            return false;
+        }
+        match &self.regex {
+            Some(regex) => regex.is_match(&entry.name),
+            None => self.text.is_empty() || entry.name.to_lowercase().starts_with(&self.text),
+        }
+    }
+
+    // Where the name text matches `name`, as byte ranges. Nothing when the query has no
+    // name text or the name doesn't match it.
+    pub fn name_spans(&self, name: &str) -> Vec<Range<usize>> {
+        if let Some(regex) = &self.regex {
+            return regex.find_iter(name).filter(|m| !m.is_empty()).map(|m| m.range()).collect();
+        }
+        if self.text.is_empty() {
+            return Vec::new();
+        }
+        // Lowercasing can change the length, so the prefix is found a character at a time
+        let mut lowered = String::new();
+        for (i, c) in name.char_indices() {
+            lowered.extend(c.to_lowercase());
+            if lowered.len() >= self.text.len() {
+                let end = i + c.len_utf8();
+                return lowered.starts_with(&self.text).then_some(0..end).into_iter().collect();
+            }
+        }
+        Vec::new()
+    }
+
+    // The same for an entry's full path: the folders it is scoped to at the start, and
+    // the name spans in the last component. Sorted and not overlapping.
+    pub fn path_spans(&self, path: &str) -> Vec<Range<usize>> {
+        let mut spans: Vec<Range<usize>> = Vec::new();
+        for folder in &self.folders {
+            let folder = folder.replace('/', "\\");
+            let folder = folder.trim_end_matches('\\');
+            let len = folder.len();
+            let under = path.get(..len).is_some_and(|start| start.eq_ignore_ascii_case(folder)) && path[len..].starts_with('\\');
+            if len > 0 && under {
+                spans.push(0..len);
+            }
+        }
+        let name_start = path.rfind('\\').map_or(0, |i| i + 1);
+        spans.extend(self.name_spans(&path[name_start..]).into_iter().map(|r| r.start + name_start..r.end + name_start));
+
+        spans.sort_by_key(|r| r.start);
+        let mut merged: Vec<Range<usize>> = Vec::new();
+        for span in spans {
+            match merged.last_mut() {
+                Some(last) if span.start <= last.end => last.end = last.end.max(span.end),
+                _ => merged.push(span),
+            }
+        }
+        merged
+    }
+
+    // Every matching entry, in index order. The query must be resolved first
+    pub fn search(&self, data: &[FileEntry]) -> Vec<FileEntry> {
+        data.par_iter()
+            .filter(|entry| self.matches(entry))
+            .cloned()
+            .collect()
+    }
+}
+
+fn is_filter_key(key: &str) -> bool {
+    matches!(
+        key.to_lowercase().as_str(),
+        "ext" | "type" | "size" | "dm" | "datemodified" | "dc" | "datecreated" | "attrib" | "parent" | "infolder"
+    )
+}
+
+fn parse_filter(key: &str, value: &str, categories: &[FileCategory]) -> Result<Filter, String> {
+    let filter = match key {
+        "ext" => Filter::Extension(
+            value.split(';')
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .filter(|e| !e.is_empty())
+                .collect(),
+        ),
+        "type" => {
+            let category = find_category(categories, value)
+                .ok_or_else(|| format!("Unknown file type '{}'", value))?;
+            Filter::Extension(category.extensions.clone())
+        }
+        "size" => Filter::Size(parse_bounds(value, parse_size)?),
+        "dm" | "datemodified" => Filter::Modified(parse_bounds(value, parse_date)?),
+        "dc" | "datecreated" => Filter::Created(parse_bounds(value, parse_date)?),
+        "attrib" => Filter::Attributes(parse_attributes(value)?),
+        "parent" | "infolder" => Filter::InFolder(Scope::Unresolved(value.to_string())),
+        _ => unreachable!("is_filter_key accepted '{}'", key),
+    };
+    Ok(filter)
+}
+
+// Turn `>x`, `>=x`, `<x`, `<=x`, `a..b` or a bare `x` into bounds, where `parse_point`
+// maps a single value to the half-open interval it covers (a day, a byte count...)
+fn parse_bounds(value: &str, parse_point: fn(&str) -> Result<(i64, i64), String>) -> Result<Bounds, String> {
+    if let Some((from, to)) = value.split_once("..") {
+        let min = if from.is_empty() { None } else { Some(parse_point(from)?.0) };
+        let max = if to.is_empty() { None } else { Some(parse_point(to)?.1) };
+        return Ok(Bounds { min, max });
+    }
+
+    let bounds = if let Some(rest) = value.strip_prefix(">=") {
+        Bounds { min: Some(parse_point(rest)?.0), max: None }
+    } else if let Some(rest) = value.strip_prefix('>') {
+        Bounds { min: Some(parse_point(rest)?.1), max: None }
+    } else if let Some(rest) = value.strip_prefix("<=") {
+        Bounds { min: None, max: Some(parse_point(rest)?.1) }
+    } else if let Some(rest) = value.strip_prefix('<') {
+        Bounds { min: None, max: Some(parse_point(rest)?.0) }
+    } else {
+        let (start, end) = parse_point(value.strip_prefix('=').unwrap_or(value))?;
+        Bounds { min: Some(start), max: Some(end) }
+    };
+    Ok(bounds)
+}
+
+// "500mb", "1.5gb", "1024". Units are binary (1kb = 1024 bytes)
+fn parse_size(value: &str) -> Result<(i64, i64), String> {
+    let lower = value.to_lowercase();
+    let split = lower.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(lower.len());
+    let (number, unit) = lower.split_at(split);
+
+    let multiplier: u64 = match unit {
+        "" | "b" => 1,
+        "k" | "kb" => 1 << 10,
+        "m" | "mb" => 1 << 20,
+        "g" | "gb" => 1 << 30,
+        "t" | "tb" => 1 << 40,
+        _ => return Err(format!("Invalid size unit in '{}'", value)),
+    };
+    let number: f64 = number.parse().map_err(|_| format!("Invalid size '{}'", value))?;
+
+    let bytes = number * multiplier as f64;
+    if bytes >= i64::MAX as f64 {
+        return Err(format!("Size '{}' is too large", value));
+    }
+    let bytes = bytes as i64;
+    let end = bytes.checked_add(1).ok_or_else(|| format!("Size '{}' is too large", value))?;
+    Ok((bytes, end))
+}
+
+// A date keyword or calendar date, as the local-time interval it covers
+fn parse_date(value: &str) -> Result<(i64, i64), String> {
+    let (start, end) = date_range(&value.to_lowercase(), Local::now().date_naive())
+        .ok_or_else(|| format!("Invalid date '{}'", value))?;
+    Ok((local_midnight(start), local_midnight(end)))
+}
+
+// The days `value` covers as seen from `today`, or None for anything that isn't a date
+// or lands outside the range of dates chrono can hold
+fn date_range(value: &str, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+    let range = match value {
+        "today" => (today, shift(today, 1)?),
+        "yesterday" => (shift(today, -1)?, today),
+        "thisweek" | "lastweek" => {
+            let monday = shift(today, -(today.weekday().num_days_from_monday() as i64))?;
+            if value == "thisweek" {
+                (monday, shift(monday, 7)?)
+            } else {
+                (shift(monday, -7)?, monday)
+            }
+        }
+        "thismonth" => {
+            let first = first_of_month(today.year(), today.month())?;
+            (first, next_month(first)?)
+        }
+        "lastmonth" => {
+            let first = first_of_month(today.year(), today.month())?;
+            let last_day = shift(first, -1)?;
+            (first_of_month(last_day.year(), last_day.month())?, first)
+        }
+        "thisyear" => (first_of_month(today.year(), 1)?, first_of_month(today.year() + 1, 1)?),
+        "lastyear" => (first_of_month(today.year() - 1, 1)?, first_of_month(today.year(), 1)?),
+        _ => {
+            if let Some(days) = value.strip_prefix("last").and_then(|r| r.strip_suffix("days")) {
+                let days: u32 = days.parse().ok()?;
+                (shift(today, 1 - days.max(1) as i64)?, shift(today, 1)?)
+            } else {
+                parse_calendar_date(value)?
+            }
+        }
+    };
+    Some(range)
+}
+
+// YYYY-MM-DD covers a day, YYYY-MM a month, YYYY a year. '/' works as a separator too
+fn parse_calendar_date(value: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let parts: Vec<&str> = value.split(['-', '/']).collect();
+    let year: i32 = parts.first()?.parse().ok()?;
+
+    match parts.len() {
+        1 => Some((first_of_month(year, 1)?, first_of_month(year.checked_add(1)?, 1)?)),
+        2 => {
+            let first = first_of_month(year, parts[1].parse().ok()?)?;
+            Some((first, next_month(first)?))
+        }
+        3 => {
+            let day = NaiveDate::from_ymd_opt(year, parts[1].parse().ok()?, parts[2].parse().ok()?)?;
+            Some((day, day.succ_opt()?))
+        }
+        _ => None,
+    }
+}
+
+fn shift(date: NaiveDate, days: i64) -> Option<NaiveDate> {
+    date.checked_add_signed(Duration::try_days(days)?)
+}
+
+fn first_of_month(year: i32, month: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(year, month, 1)
+}
+
+fn next_month(first: NaiveDate) -> Option<NaiveDate> {
+    if first.month() == 12 {
+        first_of_month(first.year().checked_add(1)?, 1)
+    } else {
+        first_of_month(first.year(), first.month() + 1)
+    }
+}
+
+fn local_midnight(date: NaiveDate) -> i64 {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    Local.from_local_datetime(&midnight)
+        .earliest()
+        .map_or_else(|| midnight.and_utc().timestamp(), |t| t.timestamp())
+}
+
+// Split on whitespace, keeping double-quoted sections (quotes removed) together
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn extension_filter_ignores_case() {
+        let index = crate::Index::builder()
+            .file(r"C:\photos\beach.JPG", 1, 0)
+            .file(r"C:\photos\Ärger.ÄRG", 1, 0)
+            .file(r"C:\photos\notes.txt", 1, 0)
+            .dir(r"C:\photos\album.jpg")
+            .build();
+        let names = |text: &str| -> Vec<&str> {
+            let query = Query::parse(text, MatchMode::Prefix, &[]).unwrap();
+            let mut names: Vec<&str> = index.entries().iter().filter(|e| query.matches(e)).map(|e| e.name.as_str()).collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names("ext:jpg"), ["beach.JPG"], "Folders have no extension");
+        assert_eq!(names("ext:JPG;ärg"), ["beach.JPG", "Ärger.ÄRG"]);
+    }
+
+    #[test]
+    fn parse_size_reads_binary_units() {
+        assert_eq!(parse_size("1024"), Ok((1024, 1025)));
+        assert_eq!(parse_size("1kb"), Ok((1024, 1025)));
+        assert_eq!(parse_size("1.5K"), Ok((1536, 1537)));
+        assert_eq!(parse_size("2gb"), Ok((2 << 30, (2 << 30) + 1)));
+    }
+
+    #[test]
+    fn parse_size_refuses_bad_and_huge_sizes() {
+        for value in ["", "kb", "1xb", "1.2.3mb", "-5", "9223372036854775807", "99999999tb", "1e30"] {
+            assert!(parse_size(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn date_range_keywords() {
+        let today = day(2024, 3, 14); // A Thursday
+        assert_eq!(date_range("today", today), Some((today, day(2024, 3, 15))));
+        assert_eq!(date_range("yesterday", today), Some((day(2024, 3, 13), today)));
+        assert_eq!(date_range("thisweek", today), Some((day(2024, 3, 11), day(2024, 3, 18))));
+        assert_eq!(date_range("lastweek", today), Some((day(2024, 3, 4), day(2024, 3, 11))));
+        assert_eq!(date_range("thismonth", today), Some((day(2024, 3, 1), day(2024, 4, 1))));
+        assert_eq!(date_range("lastmonth", day(2024, 1, 5)), Some((day(2023, 12, 1), day(2024, 1, 1))));
+        assert_eq!(date_range("lastyear", today), Some((day(2023, 1, 1), day(2024, 1, 1))));
+        assert_eq!(date_range("last7days", today), Some((day(2024, 3, 8), day(2024, 3, 15))));
+        assert_eq!(date_range("last0days", today), Some((today, day(2024, 3, 15))));
+    }
+
+    #[test]
+    fn date_range_calendar_dates() {
+        let today = day(2024, 3, 14);
+        assert_eq!(date_range("2023", today), Some((day(2023, 1, 1), day(2024, 1, 1))));
+        assert_eq!(date_range("2023-12", today), Some((day(2023, 12, 1), day(2024, 1, 1))));
+        assert_eq!(date_range("2023/02/28", today), Some((day(2023, 2, 28), day(2023, 3, 1))));
+    }
+
+    #[test]
+    fn date_range_refuses_what_isnt_a_date() {
+        let today = day(2024, 3, 14);
+        for value in ["", "tomorrow", "last-5days", "lastdays", "last99999999999days", "2023-13", "2023-02-30",
+            "262142-12", "262143", "-262144", "2147483647", "1-2-3-4"] {
+            assert_eq!(date_range(value, today), None, "{}", value);
+        }
+        assert!(parse_date("last-5days").is_err());
+    }
+
+    #[test]
+    fn parse_bounds_comparisons_and_ranges() {
+        let bounds = |value| parse_bounds(value, parse_size).unwrap();
+        assert_eq!(bounds("1kb"), Bounds { min: Some(1024), max: Some(1025) });
+        assert_eq!(bounds("=1kb"), Bounds { min: Some(1024), max: Some(1025) });
+        assert_eq!(bounds(">1kb"), Bounds { min: Some(1025), max: None });
+        assert_eq!(bounds(">=1kb"), Bounds { min: Some(1024), max: None });
+        assert_eq!(bounds("<1kb"), Bounds { min: None, max: Some(1024) });
+        assert_eq!(bounds("<=1kb"), Bounds { min: None, max: Some(1025) });
+        assert_eq!(bounds("1kb..2kb"), Bounds { min: Some(1024), max: Some(2049) });
+        assert_eq!(bounds("..2kb"), Bounds { min: None, max: Some(2049) });
+        assert_eq!(bounds("1kb.."), Bounds { min: Some(1024), max: None });
+    }
+
+    #[test]
+    fn parse_bounds_passes_errors_on() {
+        assert!(parse_bounds(">99999999tb", parse_size).is_err());
+        assert!(parse_bounds("1kb..huge", parse_size).is_err());
+        assert!(parse_bounds("<=262142-12", parse_date).is_err());
+        assert!(parse_bounds("last-5days..", parse_date).is_err());
+    }
+}
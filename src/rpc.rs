@@ -0,0 +1,255 @@
+// `deep_search --stdio`: JSON-RPC 2.0 on stdin/stdout, for editor plugins and launchers
+// that would rather spawn a child process than talk to a running instance. Messages are
+// one JSON object per line both ways. The process scans the drives itself on startup
+// and exits when stdin closes.
+//
+// Methods:
+//   search   params as in the IPC protocol (see ipc.rs). Results arrive as "results"
+//            notifications, {"search": <request id>, "results": [...]}, in batches as
+//            they are found; the response {"count": N, "cancelled": false} comes last.
+//   cancel   {"id": <request id of a search>} stops that search, which then answers
+//            with "cancelled": true. The response is whether it was still running.
+//   stats    {"ready", "reindexing", "entries", "files", "folders", "drives"}
+//   reindex  scans again and answers once the new index is in use. Searches keep using
+//            the old index until then.
+//
+// Searches made before the first scan has finished wait for it.
+
+use crate::cli::{scan_index, EXIT_OK};
+use crate::format::entry_json;
+use crate::index::{IndexSnapshot, SearchRequest, SharedIndex};
+use crate::settings::Settings;
+use deep_search::{CancelToken, Index};
+use serde_json::{json, Value};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Results go out in batches of this many, or sooner when they trickle in
+const BATCH_SIZE: usize = 100;
+const BATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+type RpcError = (i64, String);
+
+struct Server {
+    index: SharedIndex,
+    // Running searches by request id (as JSON text, so 1 and "1" stay apart)
+    searches: Mutex<HashMap<String, CancelToken>>,
+    reindexing: AtomicBool,
+    // Why the last scan failed, for searches that were waiting on it
+    scan_error: Mutex<Option<String>>,
+}
+
+pub fn run_stdio() -> i32 {
+    // No console to attach: the client hands us pipes for stdin and stdout
+    let server = Arc::new(Server {
+        index: SharedIndex::default(),
+        searches: Mutex::new(HashMap::new()),
+        reindexing: AtomicBool::new(false),
+        scan_error: Mutex::new(None),
+    });
+    server.reindex(None);
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        if !line.trim().is_empty() {
+            server.handle(&line);
+        }
+    }
+    // stdin closed: the client is gone, and running searches go with the process
+    EXIT_OK
+}
+
+impl Server {
+    fn handle(self: &Arc<Self>, line: &str) {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(e) => return send(&error_message(Value::Null, (PARSE_ERROR, format!("Parse error: {}", e)))),
+        };
+        // Requests without an id are notifications and get no response
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            return reply(Some(id.unwrap_or(Value::Null)), Err((INVALID_REQUEST, "Missing method".to_string())));
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        match method {
+            "search" => self.search(id, params),
+            "cancel" => reply(id, self.cancel(&params)),
+            "stats" => reply(id, Ok(self.stats())),
+            "reindex" => self.reindex(id),
+            _ => reply(id, Err((METHOD_NOT_FOUND, format!("Unknown method '{}'", method)))),
+        }
+    }
+
+    fn search(self: &Arc<Self>, id: Option<Value>, params: Value) {
+        let request: SearchRequest = match serde_json::from_value(params) {
+            Ok(request) => request,
+            Err(e) => return reply(id, Err((INVALID_PARAMS, format!("Invalid params: {}", e)))),
+        };
+
+        // Ids have to tell running searches apart for cancel; a second search under the
+        // id of one still running is turned down rather than taking its place
+        let cancel = CancelToken::new();
+        let key = id.as_ref().map(Value::to_string);
+        if let Some(key) = &key {
+            let started = match self.searches.lock().unwrap().entry(key.clone()) {
+                Entry::Occupied(_) => false,
+                Entry::Vacant(slot) => {
+                    slot.insert(cancel.clone());
+                    true
+                }
+            };
+            if !started {
+                return reply(id, Err((INVALID_REQUEST, format!("A search with id {} is already running", key))));
+            }
+        }
+
+        let server = self.clone();
+        thread::spawn(move || {
+            let result = server.run_search(id.as_ref().unwrap_or(&Value::Null), &request, &cancel);
+            if let Some(key) = key {
+                server.searches.lock().unwrap().remove(&key);
+            }
+            reply(id, result);
+        });
+    }
+
+    fn run_search(&self, id: &Value, request: &SearchRequest, cancel: &CancelToken) -> Result<Value, RpcError> {
+        let snapshot = self.ready_snapshot().map_err(|e| (SERVER_ERROR, e))?;
+        let query = snapshot.query(request).map_err(|e| (INVALID_PARAMS, e))?;
+        if query.is_empty() {
+            return Ok(json!({ "count": 0, "cancelled": false }));
+        }
+
+        let index = Index::from_shared(snapshot.data.clone(), snapshot.drives.clone(), snapshot.folders.clone());
+        let search = request.apply(index.search(&query)).cancel_with(cancel);
+
+        let mut batch = Vec::new();
+        let mut sent = 0;
+        let mut last_sent: Option<Instant> = None;
+        for hit in search {
+            batch.push(entry_json(hit.entry(), &hit.path()));
+            // The first hit goes out on its own so launchers can show something at once
+            if batch.len() >= BATCH_SIZE || last_sent.is_none_or(|t| t.elapsed() >= BATCH_INTERVAL) {
+                sent += batch.len();
+                notify_results(id, std::mem::take(&mut batch));
+                last_sent = Some(Instant::now());
+            }
+        }
+        if !batch.is_empty() && !cancel.is_cancelled() {
+            sent += batch.len();
+            notify_results(id, batch);
+        }
+        Ok(json!({ "count": sent, "cancelled": cancel.is_cancelled() }))
+    }
+
+    fn cancel(&self, params: &Value) -> Result<Value, RpcError> {
+        let id = params.get("id").ok_or((INVALID_PARAMS, "Missing id".to_string()))?;
+        let searches = self.searches.lock().unwrap();
+        let running = searches.get(&id.to_string());
+        if let Some(token) = running {
+            token.cancel();
+        }
+        Ok(running.is_some().into())
+    }
+
+    fn stats(&self) -> Value {
+        let snapshot = IndexSnapshot::current(&self.index);
+        let folders = snapshot.data.iter().filter(|e| e.is_dir).count();
+        json!({
+            "ready": snapshot.ready,
+            "reindexing": self.reindexing.load(Ordering::SeqCst),
+            "entries": snapshot.data.len(),
+            "files": snapshot.data.len() - folders,
+            "folders": folders,
+            "drives": *snapshot.drives,
+        })
+    }
+
+    fn reindex(self: &Arc<Self>, id: Option<Value>) {
+        if self.reindexing.swap(true, Ordering::SeqCst) {
+            return reply(id, Err((SERVER_ERROR, "A reindex is already running".to_string())));
+        }
+
+        let server = self.clone();
+        thread::spawn(move || {
+            let result = server.scan();
+            *server.scan_error.lock().unwrap() = result.as_ref().err().cloned();
+            // Only after the new index and any error are in place, see ready_snapshot
+            server.reindexing.store(false, Ordering::SeqCst);
+            reply(id, result.map_err(|e| (SERVER_ERROR, e)));
+        });
+    }
+
+    fn scan(&self) -> Result<Value, String> {
+        let started = Instant::now();
+        // Settings are read again so a reindex picks up changed rules and file lists
+        let (settings, problems) = Settings::load_repaired()?;
+        let (snapshot, scan_warnings) = scan_index(&settings)?;
+        let warnings: Vec<String> = problems.into_iter().chain(scan_warnings).collect();
+        let entries = snapshot.data.len();
+        if let Ok(mut shared) = self.index.write() {
+            *shared = snapshot;
+        }
+        Ok(json!({
+            "entries": entries,
+            "warnings": warnings,
+            "seconds": started.elapsed().as_secs_f64(),
+        }))
+    }
+
+    // Waits for the first scan; a failed one leaves nothing to search until a reindex
+    fn ready_snapshot(&self) -> Result<IndexSnapshot, String> {
+        loop {
+            let snapshot = IndexSnapshot::current(&self.index);
+            if snapshot.ready {
+                return Ok(snapshot);
+            }
+            if !self.reindexing.load(Ordering::SeqCst) {
+                let error = self.scan_error.lock().unwrap().clone();
+                return Err(error.unwrap_or_else(|| "The index has not been built".to_string()));
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+fn notify_results(id: &Value, results: Vec<Value>) {
+    send(&json!({
+        "jsonrpc": "2.0",
+        "method": "results",
+        "params": { "search": id, "results": results },
+    }));
+}
+
+fn reply(id: Option<Value>, result: Result<Value, RpcError>) {
+    let Some(id) = id else { return };
+    match result {
+        Ok(result) => send(&json!({ "jsonrpc": "2.0", "id": id, "result": result })),
+        Err(error) => send(&error_message(id, error)),
+    }
+}
+
+fn error_message(id: Value, (code, message): RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+// One message per line. Searches answer from their own threads, so each line is written
+// under the stdout lock in one go.
+fn send(message: &Value) {
+    let mut out = io::stdout().lock();
+    // A closed stdout means the client is gone; the end of stdin follows
+    let _ = writeln!(out, "{}", message).and_then(|_| out.flush());
+}
@@ -0,0 +1,76 @@
+// Saved searches: a query with the options it was made with, kept under a name in
+// settings.toml, e.g.
+//
+//   [[saved_searches]]
+//   name = "Crash dumps"
+//   query = "ext:dmp dm:today"
+//   sort = "modified"
+//   pinned = true
+//
+// Run from the Saved menu, from a tab when pinned, or with `deep_search run <name>`.
+
+use crate::index::SearchRequest;
+use crate::query::MatchMode;
+use crate::sort::SortKey;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+    pub mode: MatchMode,
+    pub file_type: Option<String>, // Category picked in the type dropdown
+    pub folder: String,            // The "In folder" box
+    pub hide_hidden: bool,
+    pub include_excluded: bool,
+    pub sort: Option<SortKey>,
+    pub reverse: bool, // Against the sort key's usual direction
+    pub pinned: bool,  // Shown as a tab above the results
+}
+
+impl Default for SavedSearch {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            query: String::new(),
+            mode: MatchMode::Prefix,
+            file_type: None,
+            folder: String::new(),
+            hide_hidden: true,
+            include_excluded: false,
+            sort: None,
+            reverse: false,
+            pinned: false,
+        }
+    }
+}
+
+impl SavedSearch {
+    // The same search as one request, with the GUI's type and folder boxes folded into
+    // the query text as type: and parent: filters
+    pub fn request(&self) -> SearchRequest {
+        let mut query = self.query.clone();
+        if let Some(file_type) = &self.file_type {
+            query.push_str(&format!(" type:\"{}\"", file_type));
+        }
+        if !self.folder.trim().is_empty() {
+            query.push_str(&format!(" parent:\"{}\"", self.folder.trim()));
+        }
+
+        SearchRequest {
+            query,
+            mode: self.mode,
+            hidden: !self.hide_hidden,
+            excluded: self.include_excluded,
+            sort: self.sort,
+            reverse: self.reverse,
+            ..SearchRequest::default()
+        }
+    }
+}
+
+// Names are matched case-insensitively
+pub fn find<'a>(saved: &'a [SavedSearch], name: &str) -> Option<&'a SavedSearch> {
+    saved.iter().find(|s| s.name.eq_ignore_ascii_case(name.trim()))
+}
@@ -0,0 +1,416 @@
+// Optional HTTP server for querying the index from browsers and scripts. Only ever
+// bound to 127.0.0.1, and searches the same shared index the GUI does.
+//
+//   GET /search?q=&offset=&count=&sort=&order=&regex=&all=   JSON results
+//   GET /export?q=&format=&columns=&sort=&order=&regex=&all=  every result as a download
+//   GET /?q=                                                 minimal HTML results page
+
+use crate::export::{Column, ExportFormat, ExportReader};
+use crate::format::{entry_json, human_size, iso_time};
+use crate::index::{IndexSnapshot, SearchRequest, SharedIndex};
+use crate::query::MatchMode;
+use crate::sort::SortKey;
+use crate::{resolve_path, FileEntry, Index};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use tiny_http::{Header, Request, Response, Server, StatusCode};
+
+const DEFAULT_COUNT: usize = 100;
+const MAX_COUNT: usize = 10_000;
+const WORKERS: usize = 4;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerSettings {
+    pub enabled: bool,
+    pub port: u16,
+    // Basic auth is required when a username is set
+    pub username: String,
+    pub password: String,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 8731,
+            username: String::new(),
+            password: String::new(),
+        }
+    }
+}
+
+// Start serving in the background. Fails only if the port can't be bound.
+pub fn start(settings: &ServerSettings, index: SharedIndex) -> Result<(), String> {
+    let address = format!("127.0.0.1:{}", settings.port);
+    let server = Server::http(&address).map_err(|e| format!("HTTP server could not listen on {}: {}", address, e))?;
+    let server = Arc::new(server);
+
+    // Compare against the header value as the browser sends it
+    let expected_auth = (!settings.username.is_empty()).then(|| {
+        let credentials = format!("{}:{}", settings.username, settings.password);
+        format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(credentials))
+    });
+    // A page elsewhere can point a name it controls at 127.0.0.1 and have the browser
+    // send it here; only requests addressed to this machine by name are answered
+    let hosts = [format!("127.0.0.1:{}", settings.port), format!("localhost:{}", settings.port), format!("[::1]:{}", settings.port)];
+
+    // A few workers so a slow query doesn't hold up the rest, without a thread per request
+    for _ in 0..WORKERS {
+        let (server, index, expected_auth, hosts) = (server.clone(), index.clone(), expected_auth.clone(), hosts.clone());
+        thread::spawn(move || {
+            while let Ok(request) = server.recv() {
+                handle(request, &index, &hosts, expected_auth.as_deref());
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle(request: Request, index: &SharedIndex, hosts: &[String], expected_auth: Option<&str>) {
+    if !host_allowed(request.headers(), hosts) {
+        let _ = request.respond(Response::from_string("Unknown host").with_status_code(403));
+        return;
+    }
+
+    if expected_auth.is_some_and(|expected| !authorized(request.headers(), expected)) {
+        let response = Response::from_string("Unauthorized")
+            .with_status_code(401)
+            .with_header(header("WWW-Authenticate", "Basic realm=\"Deep Search\""));
+        let _ = request.respond(response);
+        return;
+    }
+
+    // Answering before the first scan is done would look like a search with no results
+    let snapshot = IndexSnapshot::current(index);
+    if !snapshot.ready {
+        let response = Response::from_string("The index is still being built, try again shortly")
+            .with_status_code(503)
+            .with_header(header("Retry-After", "5"));
+        let _ = request.respond(response);
+        return;
+    }
+    let (path, query_string) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    let params = parse_query_string(query_string);
+
+    let response = match path {
+        "/search" => match search(&snapshot, &params) {
+            Ok(body) => Response::from_string(body.to_string())
+                .with_header(header("Content-Type", "application/json; charset=utf-8")),
+            Err(e) => Response::from_string(serde_json::json!({ "error": e }).to_string())
+                .with_status_code(400)
+                .with_header(header("Content-Type", "application/json; charset=utf-8")),
+        },
+        "/export" => {
+            // The response reads its rows from a search of this index as it is sent
+            let index = snapshot.index();
+            match export(&snapshot, &index, &params) {
+                Ok(response) => {
+                    let _ = request.respond(response);
+                }
+                Err(e) => {
+                    let _ = request.respond(Response::from_string(e).with_status_code(400));
+                }
+            }
+            return;
+        }
+        "/" => Response::from_string(results_page(&snapshot, &params))
+            .with_header(header("Content-Type", "text/html; charset=utf-8")),
+        _ => Response::from_string("Not found").with_status_code(404),
+    };
+    let _ = request.respond(response);
+}
+
+fn host_allowed(headers: &[Header], hosts: &[String]) -> bool {
+    headers.iter()
+        .find(|h| h.field.equiv("Host"))
+        .is_some_and(|h| hosts.iter().any(|host| h.value.as_str().eq_ignore_ascii_case(host)))
+}
+
+fn authorized(headers: &[Header], expected: &str) -> bool {
+    headers.iter().any(|h| h.field.equiv("Authorization") && same_secret(h.value.as_str(), expected))
+}
+
+// Compares every byte whatever the first difference, so the time taken doesn't give
+// away how much of a guess was right. Only the length can be told apart.
+fn same_secret(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// One page of results plus the total count
+fn run_query(index: &IndexSnapshot, params: &HashMap<String, String>) -> Result<(usize, Vec<FileEntry>), String> {
+    let count = parse_number(params, "count")?.unwrap_or(DEFAULT_COUNT).min(MAX_COUNT);
+    index.run(&search_request(params, Some(count))?)
+}
+
+fn search_request(params: &HashMap<String, String>, count: Option<usize>) -> Result<SearchRequest, String> {
+    let sort = match params.get("sort").filter(|s| !s.is_empty()) {
+        Some(sort) => Some(SortKey::parse(sort).ok_or_else(|| format!("Unknown sort key '{}'", sort))?),
+        None => None,
+    };
+    let reverse = match (sort, params.get("order").map(String::as_str)) {
+        (Some(key), Some("asc")) => key.descending_by_default(),
+        (Some(key), Some("desc")) => !key.descending_by_default(),
+        _ => false,
+    };
+
+    Ok(SearchRequest {
+        query: params.get("q").cloned().unwrap_or_default(),
+        mode: if flag(params, "regex") { MatchMode::Regex } else { MatchMode::Prefix },
+        all: flag(params, "all"),
+        hidden: flag(params, "hidden"),
+        excluded: flag(params, "excluded"),
+        sort,
+        reverse,
+        offset: parse_number(params, "offset")?.unwrap_or(0),
+        count,
+    })
+}
+
+// All results (no count cap unless one is given), pulled from the search and
+// formatted as the response is sent
+fn export<'a>(snapshot: &IndexSnapshot, index: &'a Index, params: &HashMap<String, String>) -> Result<Response<ExportReader<'a>>, String> {
+    let format = match params.get("format").filter(|f| !f.is_empty()) {
+        Some(f) => ExportFormat::parse(f).ok_or_else(|| format!("Unknown export format '{}'", f))?,
+        None => ExportFormat::Csv,
+    };
+    let columns = match params.get("columns").filter(|c| !c.is_empty()) {
+        Some(c) => Column::parse_list(c)?,
+        None => Column::DEFAULT.to_vec(),
+    };
+
+    let request = search_request(params, parse_number(params, "count")?)?;
+    let hits = snapshot.search(index, &request)?;
+    let reader = ExportReader::new(hits, format, &columns).map_err(|e| e.to_string())?;
+
+    let disposition = format!("attachment; filename=\"results.{}\"", format.extension());
+    // No length given, so tiny_http sends it chunked
+    Ok(Response::new(
+        StatusCode(200),
+        vec![header("Content-Type", format.content_type()), header("Content-Disposition", &disposition)],
+        reader,
+        None,
+        None,
+    ))
+}
+
+fn search(index: &IndexSnapshot, params: &HashMap<String, String>) -> Result<serde_json::Value, String> {
+    let (total, page) = run_query(index, params)?;
+    let results: Vec<serde_json::Value> = page.iter()
+        .map(|e| entry_json(e, &resolve_path(e, &index.data, &index.drives)))
+        .collect();
+
+    Ok(serde_json::json!({
+        "total": total,
+        "offset": parse_number(params, "offset")?.unwrap_or(0),
+        "results": results,
+    }))
+}
+
+fn results_page(index: &IndexSnapshot, params: &HashMap<String, String>) -> String {
+    let text = params.get("q").map(String::as_str).unwrap_or("");
+    let mut html = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Deep Search</title>\
+         <style>body{{font-family:sans-serif;margin:2em}}td{{padding:2px 12px 2px 0}}.m{{color:gray}}</style></head>\
+         <body><h1>Deep Search</h1><form><input name=\"q\" size=\"60\" autofocus value=\"{}\"> \
+         <button>Search</button></form>",
+        escape_html(text)
+    );
+
+    if !text.is_empty() {
+        match run_query(index, params) {
+            Ok((total, page)) => {
+                html.push_str(&format!("<p class=\"m\">{} results</p><table>", total));
+                for entry in &page {
+                    let path = resolve_path(entry, &index.data, &index.drives);
+                    let size = if entry.is_dir { String::new() } else { human_size(entry.size) };
+                    html.push_str(&format!(
+                        "<tr><td>{}</td><td class=\"m\">{}</td><td>{}</td><td class=\"m\">{}</td></tr>",
+                        escape_html(&entry.name),
+                        escape_html(&path),
+                        size,
+                        iso_time(entry.modified).unwrap_or_default()
+                    ));
+                }
+                html.push_str("</table>");
+            }
+            Err(e) => html.push_str(&format!("<p style=\"color:red\">{}</p>", escape_html(&e))),
+        }
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("static header is valid")
+}
+
+fn flag(params: &HashMap<String, String>, name: &str) -> bool {
+    matches!(params.get(name).map(String::as_str), Some("1" | "true" | "yes" | "on"))
+}
+
+fn parse_number(params: &HashMap<String, String>, name: &str) -> Result<Option<usize>, String> {
+    match params.get(name).filter(|v| !v.is_empty()) {
+        Some(value) => value.parse().map(Some).map_err(|_| format!("Invalid {} '{}'", name, value)),
+        None => Ok(None),
+    }
+}
+
+fn parse_query_string(query: &str) -> HashMap<String, String> {
+    query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+// application/x-www-form-urlencoded: '+' is a space, %XX is a byte
+fn percent_decode(text: &str) -> String {
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(high), Some(low)) => {
+                    out.push(high << 4 | low);
+                    i += 2;
+                }
+                _ => out.push(b'%'),
+            },
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_decode_reads_plus_and_escapes() {
+        assert_eq!(percent_decode("a+b"), "a b");
+        assert_eq!(percent_decode("C%3A%5Cdocs"), r"C:\docs");
+        assert_eq!(percent_decode("%2b%2B"), "++");
+        assert_eq!(percent_decode("caf%C3%A9"), "café");
+    }
+
+    #[test]
+    fn percent_decode_keeps_malformed_escapes() {
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%4"), "%4");
+        assert_eq!(percent_decode("a%4"), "a%4");
+        assert_eq!(percent_decode("%zz1"), "%zz1");
+        assert_eq!(percent_decode("%%41"), "%A");
+        assert_eq!(percent_decode("%41"), "A", "An escape right at the end");
+    }
+
+    #[test]
+    fn percent_decode_replaces_bytes_that_arent_utf8() {
+        assert_eq!(percent_decode("caf%E9"), "caf\u{FFFD}");
+        assert_eq!(percent_decode("%FF%FEx"), "\u{FFFD}\u{FFFD}x");
+    }
+
+    #[test]
+    fn query_string_splits_pairs() {
+        let params = parse_query_string("q=ext%3Ars+main&count=5&&regex&=x");
+        assert_eq!(params["q"], "ext:rs main");
+        assert_eq!(params["count"], "5");
+        assert_eq!(params["regex"], "");
+        assert_eq!(params[""], "x");
+        assert_eq!(params.len(), 4);
+        assert!(parse_query_string("").is_empty());
+    }
+
+    #[test]
+    fn flags_need_a_true_value() {
+        let params = parse_query_string("a=1&b=true&c=yes&d=on&e=0&f=&g=no");
+        for name in ["a", "b", "c", "d"] {
+            assert!(flag(&params, name), "{}", name);
+        }
+        for name in ["e", "f", "g", "missing"] {
+            assert!(!flag(&params, name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn search_request_reads_paging_and_modes() {
+        let request = search_request(&parse_query_string("q=foo&offset=20&regex=1&hidden=1"), Some(10)).unwrap();
+        assert_eq!(request.query, "foo");
+        assert_eq!(request.offset, 20);
+        assert_eq!(request.count, Some(10));
+        assert_eq!(request.mode, MatchMode::Regex);
+        assert!(request.hidden && !request.all && !request.excluded);
+
+        assert!(search_request(&parse_query_string("offset=-1"), None).unwrap_err().contains("offset"));
+        assert!(search_request(&parse_query_string("sort=colour"), None).unwrap_err().contains("colour"));
+    }
+
+    #[test]
+    fn order_is_relative_to_each_sort_keys_usual_direction() {
+        for key in SortKey::ALL {
+            let name = match key {
+                SortKey::Name => "name",
+                SortKey::Path => "path",
+                SortKey::Size => "size",
+                SortKey::Modified => "modified",
+                SortKey::Created => "created",
+            };
+            let reverse = |order: &str| {
+                let request = search_request(&parse_query_string(&format!("sort={}&order={}", name, order)), None).unwrap();
+                assert_eq!(request.sort, Some(key));
+                request.reverse
+            };
+            // Name and path go A-Z by default, sizes and dates biggest/newest first
+            let descending = key.descending_by_default();
+            assert_eq!(reverse("asc"), descending, "{} asc", name);
+            assert_eq!(reverse("desc"), !descending, "{} desc", name);
+            assert!(!reverse(""), "{} without an order", name);
+        }
+        assert!(!search_request(&parse_query_string("order=desc"), None).unwrap().reverse, "No order without a sort");
+    }
+
+    fn headers(pairs: &[(&str, &str)]) -> Vec<Header> {
+        pairs.iter().map(|(name, value)| header(name, value)).collect()
+    }
+
+    #[test]
+    fn only_local_hosts_are_answered() {
+        let hosts = ["127.0.0.1:8731".to_string(), "localhost:8731".to_string(), "[::1]:8731".to_string()];
+        for host in ["127.0.0.1:8731", "LocalHost:8731", "[::1]:8731"] {
+            assert!(host_allowed(&headers(&[("Host", host)]), &hosts), "{}", host);
+        }
+        for host in ["evil.example:8731", "localhost:8732", "127.0.0.1", "localhost.evil.example:8731"] {
+            assert!(!host_allowed(&headers(&[("Host", host)]), &hosts), "{}", host);
+        }
+        assert!(!host_allowed(&headers(&[("Accept", "*/*")]), &hosts), "No Host header");
+    }
+
+    #[test]
+    fn credentials_have_to_match_exactly() {
+        let expected = "Basic dXNlcjpwYXNz";
+        assert!(authorized(&headers(&[("Authorization", expected)]), expected));
+        for given in ["Basic dXNlcjpwYXNt", "Basic dXNlcjpwYXN", "basic dXNlcjpwYXNz", ""] {
+            assert!(!authorized(&headers(&[("Authorization", given)]), expected), "{}", given);
+        }
+        assert!(!authorized(&headers(&[("X-Authorization", expected)]), expected));
+        assert!(!authorized(&[], expected));
+    }
+}
@@ -0,0 +1,340 @@
+// User settings, stored as TOML in the per-user config directory. Every field has a
+// default, so the file only needs what differs; numbers are checked against the ranges
+// below when the file is loaded and before the settings dialog saves.
+
+use crate::exclude::ExcludeSettings;
+use crate::filetype::FileCategory;
+use crate::history::HistorySettings;
+use crate::saved::SavedSearch;
+use crate::server::ServerSettings;
+use crate::theme::ThemeSettings;
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+pub const MAX_ENTRIES: RangeInclusive<u64> = 100_000..=1_000_000_000;
+pub const BUFFER_KB: RangeInclusive<u32> = 4..=1024;
+pub const WINDOW_SIZE: RangeInclusive<f32> = 300.0..=10_000.0;
+pub const HISTORY_LIMIT: RangeInclusive<usize> = 1..=10_000;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // Extra `type:` categories, e.g.
+    //   [[categories]]
+    //   name = "Design"
+    //   extensions = ["psd", "ai", "fig"]
+    pub categories: Vec<FileCategory>,
+    // Locations left out of results, e.g.
+    //   [exclude]
+    //   patterns = ["node_modules", "*.tmp"]
+    //   [exclude.drives."D:"]
+    //   folders = ['D:\Backups']
+    pub exclude: ExcludeSettings,
+    // EFU file lists searched as read-only virtual drives, e.g.
+    //   file_lists = ['D:\Lists\buildbox.efu']
+    pub file_lists: Vec<String>,
+    // Named searches, see saved.rs
+    pub saved_searches: Vec<SavedSearch>,
+    // Search history, see history.rs
+    pub history: HistorySettings,
+    // Local HTTP search API, off unless enabled:
+    //   [server]
+    //   enabled = true
+    //   port = 8731
+    pub server: ServerSettings,
+    // Show the preview pane beside the results
+    pub preview: bool,
+    // Colors and sizes, see theme.rs
+    pub theme: ThemeSettings,
+    // Which drives are read, and how:
+    //   [scan]
+    //   skip_drives = ["E:"]
+    //   max_entries = 10000000
+    //   buffer_kb = 64
+    //   read_metadata = true
+    pub scan: ScanSettings,
+    // Size of the window when the app starts
+    pub window: WindowSettings,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanSettings {
+    pub skip_drives: Vec<String>, // Left out of the index, see the drives window
+    pub max_entries: u64, // A drive with more entries than this is given up on
+    pub buffer_kb: u32, // Read from the MFT this much at a time
+    // List every folder after the scan for sizes and dates. Without it scans finish much
+    // sooner, but sizes and dates stay unknown and size:, dm: and dc: match nothing.
+    pub read_metadata: bool,
+}
+
+impl Default for ScanSettings {
+    fn default() -> Self {
+        Self { skip_drives: Vec::new(), max_entries: 10_000_000, buffer_kb: 64, read_metadata: true }
+    }
+}
+
+impl ScanSettings {
+    pub fn skips(&self, drive: &str) -> bool {
+        self.skip_drives.iter().any(|d| d.trim_end_matches('\\').eq_ignore_ascii_case(drive))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self { width: 800.0, height: 600.0 }
+    }
+}
+
+fn fix_range<T: PartialOrd + std::fmt::Display>(problems: &mut Vec<String>, name: &str, value: &mut T, range: RangeInclusive<T>, default: T) {
+    if !range.contains(value) {
+        problems.push(format!("{} is {}, it has to be between {} and {}; using {}", name, value, range.start(), range.end(), default));
+        *value = default;
+    }
+}
+
+impl Settings {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("Deep Search").join("settings.toml"))
+    }
+
+    // A missing file is not an error, it just means defaults. Otherwise loads what can be
+    // used: values out of range are put back to their defaults and reported, so one bad
+    // value doesn't cost the rest of the file. A file that can't be read at all is an
+    // error; the caller carries on with defaults and save() keeps the file aside.
+    pub fn load_repaired() -> Result<(Settings, Vec<String>), String> {
+        match Self::path() {
+            Some(path) => Self::load_from(&path),
+            None => Ok((Settings::default(), Vec::new())),
+        }
+    }
+
+    fn load_from(path: &Path) -> Result<(Settings, Vec<String>), String> {
+        if !path.exists() {
+            return Ok((Settings::default(), Vec::new()));
+        }
+
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let mut settings: Settings = toml::from_str(&text).map_err(|e| {
+            format!("Invalid settings file {}: {}\nUsing the defaults; the file is kept as {} when settings are next saved", path.display(), e, backup_path(path).display())
+        })?;
+        let problems = settings.repair().into_iter()
+            .map(|problem| format!("Invalid settings file {}: {}", path.display(), problem))
+            .collect();
+        Ok((settings, problems))
+    }
+
+    // The first value that is out of range, if any
+    pub fn validate(&self) -> Result<(), String> {
+        self.clone().repair().into_iter().next().map_or(Ok(()), Err)
+    }
+
+    // Puts each value that is out of range back to its default, saying what was wrong
+    fn repair(&mut self) -> Vec<String> {
+        let defaults = Settings::default();
+        let mut problems = Vec::new();
+        fix_range(&mut problems, "scan.max_entries", &mut self.scan.max_entries, MAX_ENTRIES, defaults.scan.max_entries);
+        fix_range(&mut problems, "scan.buffer_kb", &mut self.scan.buffer_kb, BUFFER_KB, defaults.scan.buffer_kb);
+        fix_range(&mut problems, "window.width", &mut self.window.width, WINDOW_SIZE, defaults.window.width);
+        fix_range(&mut problems, "window.height", &mut self.window.height, WINDOW_SIZE, defaults.window.height);
+        fix_range(&mut problems, "history.limit", &mut self.history.limit, HISTORY_LIMIT, defaults.history.limit);
+        fix_range(&mut problems, "theme.font_size", &mut self.theme.font_size, crate::theme::FONT_SIZES, defaults.theme.font_size);
+        fix_range(&mut problems, "theme.row_height", &mut self.theme.row_height, crate::theme::ROW_HEIGHTS, defaults.theme.row_height);
+        if self.server.enabled && self.server.port == 0 {
+            problems.push(format!("server.port has to be set to serve searches, using {}", defaults.server.port));
+            self.server.port = defaults.server.port;
+        }
+        if let Err(e) = self.theme.check() {
+            problems.push(format!("{}, using the built-in colors", e));
+            self.theme.custom.clear();
+        }
+        problems
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("No config directory to save settings in")?;
+        self.save_to(&path)
+    }
+
+    // A file that didn't load is copied aside first, so the defaults we fell back on
+    // don't take the place of what the user wrote. If that fails nothing is saved.
+    fn save_to(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        if path.exists() && Self::load_from(path).is_err() {
+            let backup = backup_path(path);
+            std::fs::copy(path, &backup)
+                .map_err(|e| format!("Not saving settings: {} doesn't load and keeping it as {} failed: {}", path.display(), backup.display(), e))?;
+        }
+
+        let text = toml::to_string_pretty(self).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        std::fs::write(path, text).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+// settings.toml.bak beside settings.toml
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::CustomTheme;
+
+    #[test]
+    fn repair_keeps_settings_in_range() {
+        let mut settings = Settings::default();
+        assert!(settings.repair().is_empty());
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn repair_resets_each_number_out_of_range() {
+        let defaults = Settings::default();
+        let mut settings = Settings::default();
+        settings.scan.max_entries = 5;
+        settings.scan.buffer_kb = 4096;
+        settings.window.width = 10.0;
+        settings.window.height = 50_000.0;
+        settings.history.limit = 0;
+        settings.theme.font_size = 100.0;
+        settings.theme.row_height = 1.0;
+
+        let problems = settings.repair();
+        let fields = ["scan.max_entries", "scan.buffer_kb", "window.width", "window.height", "history.limit", "theme.font_size", "theme.row_height"];
+        assert_eq!(problems.len(), fields.len());
+        for (problem, field) in problems.iter().zip(fields) {
+            assert!(problem.starts_with(field), "{}", problem);
+        }
+        assert_eq!(settings.scan, defaults.scan);
+        assert_eq!(settings.window, defaults.window);
+        assert_eq!(settings.history.limit, defaults.history.limit);
+        assert_eq!(settings.theme.font_size, defaults.theme.font_size);
+        assert_eq!(settings.theme.row_height, defaults.theme.row_height);
+    }
+
+    #[test]
+    fn repair_keeps_the_ends_of_each_range() {
+        let mut settings = Settings::default();
+        settings.scan.max_entries = *MAX_ENTRIES.start();
+        settings.scan.buffer_kb = *BUFFER_KB.end();
+        settings.window.width = *WINDOW_SIZE.start();
+        settings.history.limit = *HISTORY_LIMIT.end();
+        assert!(settings.repair().is_empty());
+        assert_eq!(settings.scan.buffer_kb, *BUFFER_KB.end());
+    }
+
+    #[test]
+    fn repair_sets_a_port_for_an_enabled_server() {
+        let mut settings = Settings::default();
+        settings.server.port = 0;
+        assert!(settings.repair().is_empty(), "A disabled server needs no port");
+
+        settings.server.enabled = true;
+        let problems = settings.repair();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("server.port"));
+        assert_eq!(settings.server.port, ServerSettings::default().port);
+    }
+
+    #[test]
+    fn repair_drops_a_missing_or_broken_custom_theme() {
+        let mut settings = Settings::default();
+        settings.theme.custom = "Nope".to_string();
+        assert_eq!(settings.repair().len(), 1);
+        assert!(settings.theme.custom.is_empty());
+
+        let theme = CustomTheme { name: "Broken".to_string(), background: Some("not a color".to_string()), ..Default::default() };
+        settings.theme.themes.push(theme);
+        settings.theme.custom = "Broken".to_string();
+        assert_eq!(settings.repair().len(), 1);
+        assert!(settings.theme.custom.is_empty());
+        assert_eq!(settings.theme.themes.len(), 1, "Only the choice is dropped");
+    }
+
+    #[test]
+    fn validate_reports_the_first_problem() {
+        let mut settings = Settings::default();
+        settings.window.height = 1.0;
+        settings.history.limit = 0;
+        assert!(settings.validate().unwrap_err().starts_with("window.height"));
+        assert_eq!(settings.window.height, 1.0, "Validating changes nothing");
+    }
+
+    // A fresh folder under the system temp folder, removed again when dropped
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Scratch {
+            let dir = std::env::temp_dir().join(format!("deep_search_settings_{}_{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Scratch(dir)
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn loading_repairs_and_reports_values_out_of_range() {
+        let scratch = Scratch::new("repair");
+        let path = scratch.0.join("settings.toml");
+        std::fs::write(&path, "preview = true\n[scan]\nbuffer_kb = 1\n").unwrap();
+
+        let (settings, problems) = Settings::load_from(&path).unwrap();
+        assert!(settings.preview, "The rest of the file is kept");
+        assert_eq!(settings.scan.buffer_kb, ScanSettings::default().buffer_kb);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("scan.buffer_kb"));
+        assert!(Settings::load_from(&scratch.0.join("missing.toml")).unwrap().1.is_empty());
+    }
+
+    #[test]
+    fn saving_over_a_broken_file_keeps_it_aside() {
+        let scratch = Scratch::new("broken");
+        let path = scratch.0.join("settings.toml");
+        let broken = "preview = true\n[scan\nbuffer_kb = 128\n";
+        std::fs::write(&path, broken).unwrap();
+
+        let error = Settings::load_from(&path).unwrap_err();
+        assert!(error.contains("settings.toml.bak"), "{}", error);
+
+        Settings::default().save_to(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(backup_path(&path)).unwrap(), broken);
+        assert!(Settings::load_from(&path).is_ok());
+
+        // Once the file loads again, saving just saves
+        Settings { preview: true, ..Default::default() }.save_to(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(backup_path(&path)).unwrap(), broken);
+        assert!(Settings::load_from(&path).unwrap().0.preview);
+    }
+
+    #[test]
+    fn saving_is_refused_when_a_broken_file_cant_be_kept() {
+        let scratch = Scratch::new("refused");
+        let path = scratch.0.join("settings.toml");
+        std::fs::write(&path, "not = [toml").unwrap();
+        // A folder where the backup would go makes the copy fail
+        std::fs::create_dir(backup_path(&path)).unwrap();
+
+        assert!(Settings::default().save_to(&path).unwrap_err().starts_with("Not saving settings"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "not = [toml");
+    }
+}
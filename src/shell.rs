@@ -0,0 +1,170 @@
+// Handing results to the desktop: opening them, showing them in their folder, a
+// terminal there, and the Recycle Bin. Every path goes through actions::checked_path
+// first, see there.
+
+use deep_search::actions;
+
+pub use platform::{open, open_terminal, open_with, show_in_folder};
+
+// Deleted files go where the user can get them back; nothing is deleted outright
+pub fn move_to_trash(paths: &[String]) -> Result<(), String> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let paths = paths.iter().map(|p| actions::checked_path(p)).collect::<Result<Vec<_>, _>>()?;
+    actions::check_trash(&paths)?;
+    platform::trash(&paths)
+}
+
+#[cfg(windows)]
+mod platform {
+    use deep_search::actions::{checked_path, explorer_select_argument};
+    use std::ffi::OsString;
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::UI::Shell::{
+        SHFileOperationW, ShellExecuteW, FOF_ALLOWUNDO, FOF_NOCONFIRMATION, FOF_WANTNUKEWARNING, FO_DELETE, SHFILEOPSTRUCTW,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::SW_SHOW;
+
+    fn wide(text: &str) -> Vec<u16> {
+        OsString::from(text).encode_wide().chain(Some(0)).collect()
+    }
+
+    fn optional(text: &Option<Vec<u16>>) -> PCWSTR {
+        text.as_ref().map_or(PCWSTR::null(), |t| PCWSTR(t.as_ptr()))
+    }
+
+    // ShellExecuteW rather than Command, which would go through cmd.exe-style argument
+    // parsing. The file is taken as a whole, never split into arguments.
+    fn shell_execute(verb: &str, file: &str, params: Option<&str>, directory: Option<&str>) -> Result<(), String> {
+        let verb = wide(verb);
+        let file_wide = wide(file);
+        let (params, directory) = (params.map(wide), directory.map(wide));
+        let result = unsafe {
+            ShellExecuteW(
+                None,
+                PCWSTR(verb.as_ptr()),
+                PCWSTR(file_wide.as_ptr()),
+                optional(&params),
+                optional(&directory),
+                SW_SHOW
+            )
+        };
+        // Values up to 32 are error codes
+        match result.0 as isize {
+            code @ 0..=32 => Err(format!("Failed to open {} (error {})", file, code)),
+            _ => Ok(()),
+        }
+    }
+
+    // A file with its default program, or a folder in Explorer
+    pub fn open(path: &str) -> Result<(), String> {
+        shell_execute("open", &checked_path(path)?, None, None)
+    }
+
+    // The "Open with" dialog to pick a program
+    pub fn open_with(path: &str) -> Result<(), String> {
+        let path = checked_path(path)?;
+        if !std::path::Path::new(&path).is_file() {
+            return Err(format!("{} is not a file", path));
+        }
+        shell_execute("openas", &path, None, None)
+    }
+
+    // A command prompt started in the folder. The folder goes in as the working
+    // directory, not on a command line.
+    pub fn open_terminal(folder: &str) -> Result<(), String> {
+        let folder = checked_path(folder)?;
+        if !std::path::Path::new(&folder).is_dir() {
+            return Err(format!("{} is not a folder", folder));
+        }
+        shell_execute("open", "cmd.exe", None, Some(&folder))
+    }
+
+    // Explorer on the containing folder, with the entry selected
+    pub fn show_in_folder(path: &str) -> Result<(), String> {
+        let argument = explorer_select_argument(&checked_path(path)?)?;
+        shell_execute("open", "explorer.exe", Some(&argument), None)
+    }
+
+    // The shell's own delete, which keeps it undoable from the Recycle Bin. We have asked
+    // already; the shell only asks again for items too big for it or on drives without one.
+    pub fn trash(paths: &[String]) -> Result<(), String> {
+        // A list of null-terminated paths, ended by one more null
+        let mut from: Vec<u16> = paths.iter().flat_map(|p| wide(p)).collect();
+        from.push(0);
+        let mut op = SHFILEOPSTRUCTW {
+            wFunc: FO_DELETE,
+            pFrom: PCWSTR(from.as_ptr()),
+            fFlags: (FOF_ALLOWUNDO.0 | FOF_NOCONFIRMATION.0 | FOF_WANTNUKEWARNING.0) as u16,
+            ..Default::default()
+        };
+        let result = unsafe { SHFileOperationW(&mut op) };
+        if op.fAnyOperationsAborted.as_bool() {
+            Err("Cancelled".to_string())
+        } else if result != 0 {
+            Err(format!("Failed to move to the Recycle Bin (error {:#x})", result))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// Elsewhere the desktop's own tools do the work, run directly rather than through a shell
+#[cfg(not(windows))]
+mod platform {
+    use deep_search::actions::checked_path;
+    use std::path::Path;
+    use std::process::Command;
+
+    fn run(program: &str, args: &[&str]) -> Result<(), String> {
+        let status = Command::new(program)
+            .args(args)
+            .status()
+            .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("{} failed ({})", program, status))
+        }
+    }
+
+    // Checked paths are absolute, so xdg-open can't take one for an option
+    pub fn open(path: &str) -> Result<(), String> {
+        run("xdg-open", &[&checked_path(path)?])
+    }
+
+    // File managers differ in how to select an entry, so this opens the folder
+    pub fn show_in_folder(path: &str) -> Result<(), String> {
+        let path = checked_path(path)?;
+        let folder = Path::new(&path).parent().map_or(path.clone(), |p| p.to_string_lossy().into_owned());
+        run("xdg-open", &[&folder])
+    }
+
+    pub fn open_with(_path: &str) -> Result<(), String> {
+        Err("Choosing a program to open with isn't available here".to_string())
+    }
+
+    // The terminal the desktop prefers, else the first common one found
+    pub fn open_terminal(folder: &str) -> Result<(), String> {
+        let folder = checked_path(folder)?;
+        if !Path::new(&folder).is_dir() {
+            return Err(format!("{} is not a folder", folder));
+        }
+        let preferred = std::env::var("TERMINAL").ok();
+        let candidates = preferred.iter().map(String::as_str).chain(["x-terminal-emulator", "gnome-terminal", "konsole", "xterm"]);
+        for terminal in candidates {
+            if Command::new(terminal).current_dir(&folder).spawn().is_ok() {
+                return Ok(());
+            }
+        }
+        Err("No terminal found; set $TERMINAL to one".to_string())
+    }
+
+    pub fn trash(paths: &[String]) -> Result<(), String> {
+        let mut args = vec!["trash", "--"];
+        args.extend(paths.iter().map(String::as_str));
+        run("gio", &args)
+    }
+}
@@ -0,0 +1,61 @@
+// Result ordering, shared by the GUI and the command line
+
+use crate::{resolve_path, FileEntry};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    Name,
+    Path,
+    Size,
+    Modified,
+    Created,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 5] = [SortKey::Name, SortKey::Path, SortKey::Size, SortKey::Modified, SortKey::Created];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Name => "Name",
+            SortKey::Path => "Path",
+            SortKey::Size => "Size",
+            SortKey::Modified => "Date modified",
+            SortKey::Created => "Date created",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<SortKey> {
+        match value.to_lowercase().as_str() {
+            "name" => Some(SortKey::Name),
+            "path" => Some(SortKey::Path),
+            "size" => Some(SortKey::Size),
+            "modified" | "dm" | "date" => Some(SortKey::Modified),
+            "created" | "dc" => Some(SortKey::Created),
+            _ => None,
+        }
+    }
+
+    // Biggest and newest first is what people want from size/date sorts
+    pub fn descending_by_default(self) -> bool {
+        matches!(self, SortKey::Size | SortKey::Modified | SortKey::Created)
+    }
+}
+
+// Works on entries or references to them
+pub fn sort_results<T: Borrow<FileEntry> + Send>(results: &mut [T], key: SortKey, descending: bool, data: &[FileEntry], drives: &[String]) {
+    match key {
+        SortKey::Name => results.par_sort_by_cached_key(|e| e.borrow().name.to_lowercase()),
+        // Resolving a path walks the parent chain, so do it once per entry
+        SortKey::Path => results.par_sort_by_cached_key(|e| resolve_path(e.borrow(), data, drives).to_lowercase()),
+        SortKey::Size => results.par_sort_by_key(|e| e.borrow().size),
+        SortKey::Modified => results.par_sort_by_key(|e| e.borrow().modified),
+        SortKey::Created => results.par_sort_by_key(|e| e.borrow().created),
+    }
+    if descending {
+        results.reverse();
+    }
+}
@@ -0,0 +1,240 @@
+// Colors and text sizes of the window, from the [theme] table of the settings file, e.g.
+//   [theme]
+//   mode = "system"        # "dark", "light" or "system"
+//   font_size = 16.0
+//   row_height = 28.0
+//   custom = "Solarized"   # one of the themes below, instead of the mode
+//   [[theme.themes]]
+//   name = "Solarized"
+//   background = "#002b36"
+//   text = "#93a1a1"
+//   accent = "#268bd2"
+//
+// Both the dark and the light style are handed to egui, so in system mode it switches
+// along with the OS by itself. Nothing here runs per frame; the app applies the theme
+// again only when the settings change.
+
+use eframe::egui::{self, Color32, Theme, ThemePreference, Visuals};
+use serde::{Deserialize, Serialize};
+
+pub const FONT_SIZES: std::ops::RangeInclusive<f32> = 9.0..=24.0;
+pub const ROW_HEIGHTS: std::ops::RangeInclusive<f32> = 18.0..=48.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeMode {
+    #[default]
+    Dark,
+    Light,
+    System, // Whatever the OS is set to
+}
+
+impl ThemeMode {
+    pub const ALL: [ThemeMode; 3] = [ThemeMode::Dark, ThemeMode::Light, ThemeMode::System];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ThemeMode::Dark => "Dark",
+            ThemeMode::Light => "Light",
+            ThemeMode::System => "Follow system",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeSettings {
+    pub mode: ThemeMode,
+    pub custom: String, // Name of one of `themes` to use instead of the mode, empty for none
+    pub themes: Vec<CustomTheme>,
+    pub font_size: f32, // Of normal text, in points; the rest scales along
+    pub row_height: f32, // Of result rows
+}
+
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        Self {
+            mode: ThemeMode::Dark,
+            custom: String::new(),
+            themes: Vec::new(),
+            font_size: 12.5, // egui's own
+            row_height: 24.0,
+        }
+    }
+}
+
+impl ThemeSettings {
+    pub fn row_height(&self) -> f32 {
+        self.row_height.clamp(*ROW_HEIGHTS.start(), *ROW_HEIGHTS.end())
+    }
+
+    // That the custom theme exists and every theme's colors can be read
+    pub fn check(&self) -> Result<(), String> {
+        if !self.custom.is_empty() && !self.themes.iter().any(|t| t.name == self.custom) {
+            return Err(format!("There is no theme named \"{}\" in the settings file", self.custom));
+        }
+        self.themes.iter().try_for_each(|t| t.visuals().map(|_| ()))
+    }
+}
+
+// A user theme. Colors are "#rrggbb"; the ones left out keep the built-in dark or
+// light color the theme starts from.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CustomTheme {
+    pub name: String,
+    pub light: bool, // Start from the light colors rather than the dark ones
+    pub background: Option<String>,
+    pub text: Option<String>,
+    pub accent: Option<String>, // Result names, links and the selection count
+    pub selection: Option<String>, // Selected rows and text
+    pub stripe: Option<String>, // Every other result row
+    pub warning: Option<String>, // Match highlights, attribute letters and offline entries
+    pub error: Option<String>,
+}
+
+impl CustomTheme {
+    fn visuals(&self) -> Result<Visuals, String> {
+        let mut visuals = if self.light { light() } else { dark() };
+        let color = |value: &Option<String>| -> Result<Option<Color32>, String> {
+            value.as_deref()
+                .map(|hex| Color32::from_hex(hex).map_err(|_| format!("Theme \"{}\": \"{}\" is not a #rrggbb color", self.name, hex)))
+                .transpose()
+        };
+
+        if let Some(background) = color(&self.background)? {
+            visuals.panel_fill = background;
+            visuals.window_fill = background;
+            visuals.widgets.noninteractive.bg_fill = background;
+        }
+        if let Some(text) = color(&self.text)? {
+            visuals.override_text_color = Some(text);
+        }
+        if let Some(accent) = color(&self.accent)? {
+            visuals.hyperlink_color = accent;
+        }
+        if let Some(selection) = color(&self.selection)? {
+            visuals.selection.bg_fill = selection;
+        }
+        if let Some(stripe) = color(&self.stripe)? {
+            visuals.faint_bg_color = stripe;
+        }
+        if let Some(warning) = color(&self.warning)? {
+            visuals.warn_fg_color = warning;
+        }
+        if let Some(error) = color(&self.error)? {
+            visuals.error_fg_color = error;
+        }
+        Ok(visuals)
+    }
+}
+
+// The app's own dark look
+fn dark() -> Visuals {
+    let mut visuals = Visuals::dark();
+    let background = Color32::from_rgb(30, 30, 35); // Dark blue-ish grey
+    visuals.window_fill = background;
+    visuals.panel_fill = background;
+    visuals.widgets.noninteractive.bg_fill = background;
+    visuals.hyperlink_color = Color32::LIGHT_BLUE;
+    visuals.selection.bg_fill = Color32::from_rgb(45, 75, 120);
+    visuals.faint_bg_color = Color32::from_rgb(45, 45, 50);
+    visuals.warn_fg_color = Color32::from_rgb(230, 160, 60);
+    visuals.error_fg_color = Color32::LIGHT_RED;
+    visuals
+}
+
+fn light() -> Visuals {
+    let mut visuals = Visuals::light();
+    visuals.hyperlink_color = Color32::from_rgb(20, 90, 180);
+    visuals.selection.bg_fill = Color32::from_rgb(180, 205, 240);
+    visuals.faint_bg_color = Color32::from_rgb(240, 240, 244);
+    visuals.warn_fg_color = Color32::from_rgb(170, 95, 0);
+    visuals.error_fg_color = Color32::from_rgb(200, 30, 30);
+    visuals
+}
+
+// Messages about things that went right; egui has no color for these
+pub fn success_color(visuals: &Visuals) -> Color32 {
+    if visuals.dark_mode { Color32::LIGHT_GREEN } else { Color32::DARK_GREEN }
+}
+
+// Mode, custom theme and size choices, for the Theme menu and the settings window.
+// True once a choice is made or a slider let go, not on every step of a drag.
+pub fn controls(ui: &mut egui::Ui, theme: &mut ThemeSettings) -> bool {
+    let mut done = false;
+    for mode in ThemeMode::ALL {
+        if ui.radio(theme.custom.is_empty() && theme.mode == mode, mode.label()).clicked() {
+            theme.mode = mode;
+            theme.custom.clear();
+            done = true;
+        }
+    }
+    if !theme.themes.is_empty() {
+        ui.separator();
+        for name in theme.themes.iter().map(|t| t.name.clone()).collect::<Vec<_>>() {
+            if ui.radio(theme.custom == name, &name).clicked() {
+                theme.custom = name;
+                done = true;
+            }
+        }
+    }
+
+    ui.separator();
+    let mut slider = |ui: &mut egui::Ui, value: &mut f32, range, label: &str| {
+        let response = ui.add(egui::Slider::new(value, range).step_by(0.5).text(label));
+        done |= response.drag_stopped() || (response.changed() && !response.dragged());
+    };
+    slider(ui, &mut theme.font_size, FONT_SIZES, "Text size");
+    slider(ui, &mut theme.row_height, ROW_HEIGHTS, "Row height");
+    done
+}
+
+// Hands the styles to egui. On an error (an unknown or broken custom theme) the
+// built-in colors are used and the error is returned to show.
+pub fn apply(ctx: &egui::Context, settings: &ThemeSettings) -> Result<(), String> {
+    let mut preference = match settings.mode {
+        ThemeMode::Dark => ThemePreference::Dark,
+        ThemeMode::Light => ThemePreference::Light,
+        ThemeMode::System => ThemePreference::System,
+    };
+    let (mut dark_visuals, mut light_visuals) = (dark(), light());
+
+    let mut result = Ok(());
+    if !settings.custom.is_empty() {
+        let custom = settings.themes.iter()
+            .find(|t| t.name == settings.custom)
+            .ok_or_else(|| format!("There is no theme named \"{}\" in the settings file", settings.custom))
+            .and_then(|theme| Ok((theme.light, theme.visuals()?)));
+        match custom {
+            Ok((true, visuals)) => {
+                light_visuals = visuals;
+                preference = ThemePreference::Light;
+            }
+            Ok((false, visuals)) => {
+                dark_visuals = visuals;
+                preference = ThemePreference::Dark;
+            }
+            Err(e) => result = Err(e),
+        }
+    }
+
+    ctx.set_visuals_of(Theme::Dark, dark_visuals);
+    ctx.set_visuals_of(Theme::Light, light_visuals);
+    ctx.set_theme(preference);
+
+    // Every text style grows or shrinks with the body text
+    let defaults = egui::Style::default().text_styles;
+    let body = defaults.get(&egui::TextStyle::Body).map_or(12.5, |font| font.size);
+    let scale = settings.font_size.clamp(*FONT_SIZES.start(), *FONT_SIZES.end()) / body;
+    ctx.all_styles_mut(|style| {
+        for (text_style, font) in &defaults {
+            let size = match text_style {
+                egui::TextStyle::Small => 11.0, // Paths in result rows, notes and messages
+                _ => font.size,
+            };
+            style.text_styles.insert(text_style.clone(), egui::FontId::new(size * scale, font.family.clone()));
+        }
+    });
+    result
+}